@@ -1,46 +1,212 @@
-use crate::token::Token;
+pub mod visit;
 
-#[derive(Debug,PartialEq, Eq, Clone)]
+use std::fmt;
+
+use crate::token::{self, Token};
+
+#[derive(Debug,PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement{
     LetStatement(Identifier, Expression),
     ReturnStatement(Expression),
-    ExpressionStatement(Expression)
+    ExpressionStatement(Expression),
+    WhileStatement(Expression, BlockStatement), // Condition, Body
+    ForStatement(Identifier, Option<Identifier>, Expression, BlockStatement), // Name(s), Iterable, Body
+    BreakStatement,
+    ContinueStatement,
 }
-#[derive(Debug,PartialEq, Eq, Clone)]
+#[derive(Debug,PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockStatement{
     pub statements: Vec<Statement>
 }
 
-#[derive(Debug,PartialEq, Eq, Clone)]
+#[derive(Debug,PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression{
     EMPTY,
     Ident(String),
-    Integer(i32),
+    Integer(i64),
+    Float(f64),
+    Str(String),
     Prefix(Token, Box<Expression>), // Operator, RightExpression
     Infix(Token, Box<Expression>, Box<Expression>),//Op,left,right
     Boolean(bool),
     Null,
     If(Box<Expression>, Option<BlockStatement>, Option<BlockStatement>), // (Condition, Consequence, Alternative)
     Function(Vec<Identifier>, Option<BlockStatement>),
-    Call(Box<Expression>, Vec<Expression>) // Identifier or function literal
+    Call(Box<Expression>, Vec<Expression>), // Identifier or function literal
+    Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>), // (Key, Value) pairs in source order
+    Index(Box<Expression>, Box<Expression>), // Left, Index
+    Assign(Token, Identifier, Box<Expression>), // `=`, `+=`, `-=`, `*=` or `/=`, target, value
+    Range(Box<Expression>, Box<Expression>, bool), // Start, end, whether `..=` includes the end
 }
 
-#[derive(Debug,PartialEq, Eq, Clone)]
+#[derive(Debug,PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Identifier{
     pub token: Token,
     pub value: String,
 }
+#[derive(Debug,PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program{
     pub statements: Vec<Statement>
 }
 
+// Display prints Monkey source that parses back to the same tree. Every
+// prefix, infix, index, assignment and range expression is parenthesized, so the output shows
+// how the parser grouped things, e.g. `((a + (b * c)) + d)`.
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_statements(f, &self.statements, "\n")
+    }
+}
+
+impl fmt::Display for BlockStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.statements.is_empty() {
+            return write!(f, "{{ }}");
+        }
+        write!(f, "{{ ")?;
+        write_statements(f, &self.statements, " ")?;
+        write!(f, " }}")
+    }
+}
+
+// Expression statements only get a `;` when another statement follows,
+// which they would otherwise run into.
+fn write_statements(f: &mut fmt::Formatter<'_>, statements: &[Statement], separator: &str) -> fmt::Result {
+    for (i, statement) in statements.iter().enumerate() {
+        if i > 0 {
+            if let Statement::ExpressionStatement(_) = statements[i - 1] {
+                write!(f, ";")?;
+            }
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", statement)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::LetStatement(name, value) => write!(f, "let {} = {};", name, value),
+            Statement::ReturnStatement(value) => write!(f, "return {};", value),
+            Statement::ExpressionStatement(expr) => write!(f, "{}", expr),
+            Statement::WhileStatement(condition, body) => write!(f, "while ({}) {}", condition, body),
+            Statement::ForStatement(name, value, iterable, body) => {
+                write!(f, "for ({}", name)?;
+                if let Some(value) = value {
+                    write!(f, ", {}", value)?;
+                }
+                write!(f, " in {}) {}", iterable, body)
+            }
+            Statement::BreakStatement => write!(f, "break;"),
+            Statement::ContinueStatement => write!(f, "continue;"),
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+fn write_block(f: &mut fmt::Formatter<'_>, block: &Option<BlockStatement>) -> fmt::Result {
+    match block {
+        Some(block) => write!(f, "{}", block),
+        None => write!(f, "{{ }}"),
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::EMPTY => Ok(()),
+            Expression::Ident(name) => write!(f, "{}", name),
+            Expression::Integer(x) => write!(f, "{}", x),
+            Expression::Float(x) => write!(f, "{:?}", x),
+            Expression::Str(x) => token::write_str_literal(f, x),
+            Expression::Prefix(op, right) => write!(f, "({}{})", op, right),
+            Expression::Infix(op, left, right) => write!(f, "({} {} {})", left, op, right),
+            Expression::Boolean(x) => write!(f, "{}", x),
+            Expression::Null => write!(f, "null"),
+            Expression::If(condition, consequence, alternative) => {
+                write!(f, "if ({}) ", condition)?;
+                write_block(f, consequence)?;
+                if alternative.is_some() {
+                    write!(f, " else ")?;
+                    write_block(f, alternative)?;
+                }
+                Ok(())
+            }
+            Expression::Function(params, body) => {
+                write!(f, "fn(")?;
+                write_list(f, params)?;
+                write!(f, ") ")?;
+                write_block(f, body)
+            }
+            Expression::Call(function, args) => {
+                write!(f, "{}(", function)?;
+                write_list(f, args)?;
+                write!(f, ")")
+            }
+            Expression::Array(elements) => {
+                write!(f, "[")?;
+                write_list(f, elements)?;
+                write!(f, "]")
+            }
+            Expression::Hash(pairs) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Expression::Index(left, index) => write!(f, "({}[{}])", left, index),
+            Expression::Assign(op, name, value) => write!(f, "({} {} {})", name, op, value),
+            Expression::Range(start, end, inclusive) => {
+                write!(f, "({}{}{})", start, if *inclusive { "..=" } else { ".." }, end)
+            }
+        }
+    }
+}
+
 #[derive(PartialEq, PartialOrd,Debug)]
 pub enum Sticky {
     LOWEST,
+    ASSIGN,
+    RANGE,
+    OR,
+    AND,
     EQUALS,
     LESSGREATER,
+    BITOR,
+    BITXOR,
+    BITAND,
+    SHIFT,
     SUM,
     PRODUCT,
     PREFIX,
-    CALL
+    POWER,
+    CALL,
+    INDEX
 }
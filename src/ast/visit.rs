@@ -0,0 +1,444 @@
+//! Generic walks over the AST.
+//!
+//! `Visitor` looks at nodes, `VisitMut` changes them in place and `Fold`
+//! rebuilds the tree from owned nodes. Every method defaults to visiting the
+//! node's children through the matching `walk_*`/`fold_*` function, so an
+//! implementation only overrides the nodes it cares about and calls that
+//! function to keep descending.
+
+use super::{BlockStatement, Expression, Identifier, Program, Statement};
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_block(&mut self, block: &BlockStatement) {
+        walk_block(self, block)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::LetStatement(name, value) => {
+            visitor.visit_identifier(name);
+            visitor.visit_expression(value);
+        }
+        Statement::ReturnStatement(value) => visitor.visit_expression(value),
+        Statement::ExpressionStatement(expr) => visitor.visit_expression(expr),
+        Statement::WhileStatement(condition, body) => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(body);
+        }
+        Statement::ForStatement(name, value, iterable, body) => {
+            visitor.visit_identifier(name);
+            if let Some(value) = value {
+                visitor.visit_identifier(value);
+            }
+            visitor.visit_expression(iterable);
+            visitor.visit_block(body);
+        }
+        Statement::BreakStatement | Statement::ContinueStatement => {}
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::EMPTY
+        | Expression::Ident(_)
+        | Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Str(_)
+        | Expression::Boolean(_)
+        | Expression::Null => {}
+        Expression::Prefix(_, right) => visitor.visit_expression(right),
+        Expression::Infix(_, left, right) => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::If(condition, consequence, alternative) => {
+            visitor.visit_expression(condition);
+            for block in consequence.iter().chain(alternative) {
+                visitor.visit_block(block);
+            }
+        }
+        Expression::Function(params, body) => {
+            for param in params {
+                visitor.visit_identifier(param);
+            }
+            if let Some(body) = body {
+                visitor.visit_block(body);
+            }
+        }
+        Expression::Call(function, args) => {
+            visitor.visit_expression(function);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Array(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Hash(pairs) => {
+            for (key, value) in pairs {
+                visitor.visit_expression(key);
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Index(left, index) => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(index);
+        }
+        Expression::Assign(_, name, value) => {
+            visitor.visit_identifier(name);
+            visitor.visit_expression(value);
+        }
+        Expression::Range(start, end, _) => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+        }
+    }
+}
+
+pub trait VisitMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut BlockStatement) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr)
+    }
+
+    fn visit_identifier_mut(&mut self, _identifier: &mut Identifier) {}
+}
+
+pub fn walk_program_mut<V: VisitMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for statement in &mut program.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::LetStatement(name, value) => {
+            visitor.visit_identifier_mut(name);
+            visitor.visit_expression_mut(value);
+        }
+        Statement::ReturnStatement(value) => visitor.visit_expression_mut(value),
+        Statement::ExpressionStatement(expr) => visitor.visit_expression_mut(expr),
+        Statement::WhileStatement(condition, body) => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_block_mut(body);
+        }
+        Statement::ForStatement(name, value, iterable, body) => {
+            visitor.visit_identifier_mut(name);
+            if let Some(value) = value {
+                visitor.visit_identifier_mut(value);
+            }
+            visitor.visit_expression_mut(iterable);
+            visitor.visit_block_mut(body);
+        }
+        Statement::BreakStatement | Statement::ContinueStatement => {}
+    }
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(visitor: &mut V, block: &mut BlockStatement) {
+    for statement in &mut block.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::EMPTY
+        | Expression::Ident(_)
+        | Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Str(_)
+        | Expression::Boolean(_)
+        | Expression::Null => {}
+        Expression::Prefix(_, right) => visitor.visit_expression_mut(right),
+        Expression::Infix(_, left, right) => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        Expression::If(condition, consequence, alternative) => {
+            visitor.visit_expression_mut(condition);
+            for block in consequence.iter_mut().chain(alternative) {
+                visitor.visit_block_mut(block);
+            }
+        }
+        Expression::Function(params, body) => {
+            for param in params {
+                visitor.visit_identifier_mut(param);
+            }
+            if let Some(body) = body {
+                visitor.visit_block_mut(body);
+            }
+        }
+        Expression::Call(function, args) => {
+            visitor.visit_expression_mut(function);
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::Array(elements) => {
+            for element in elements {
+                visitor.visit_expression_mut(element);
+            }
+        }
+        Expression::Hash(pairs) => {
+            for (key, value) in pairs {
+                visitor.visit_expression_mut(key);
+                visitor.visit_expression_mut(value);
+            }
+        }
+        Expression::Index(left, index) => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(index);
+        }
+        Expression::Assign(_, name, value) => {
+            visitor.visit_identifier_mut(name);
+            visitor.visit_expression_mut(value);
+        }
+        Expression::Range(start, end, _) => {
+            visitor.visit_expression_mut(start);
+            visitor.visit_expression_mut(end);
+        }
+    }
+}
+
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement(self, statement)
+    }
+
+    fn fold_block(&mut self, block: BlockStatement) -> BlockStatement {
+        fold_block(self, block)
+    }
+
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        fold_expression(self, expr)
+    }
+
+    fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+        identifier
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: program.statements.into_iter().map(|s| folder.fold_statement(s)).collect(),
+    }
+}
+
+pub fn fold_statement<F: Fold + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::LetStatement(name, value) => {
+            Statement::LetStatement(folder.fold_identifier(name), folder.fold_expression(value))
+        }
+        Statement::ReturnStatement(value) => Statement::ReturnStatement(folder.fold_expression(value)),
+        Statement::ExpressionStatement(expr) => Statement::ExpressionStatement(folder.fold_expression(expr)),
+        Statement::WhileStatement(condition, body) => {
+            Statement::WhileStatement(folder.fold_expression(condition), folder.fold_block(body))
+        }
+        Statement::ForStatement(name, value, iterable, body) => {
+            let name = folder.fold_identifier(name);
+            let value = value.map(|value| folder.fold_identifier(value));
+            let iterable = folder.fold_expression(iterable);
+            Statement::ForStatement(name, value, iterable, folder.fold_block(body))
+        }
+        Statement::BreakStatement | Statement::ContinueStatement => statement,
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(folder: &mut F, block: BlockStatement) -> BlockStatement {
+    BlockStatement {
+        statements: block.statements.into_iter().map(|s| folder.fold_statement(s)).collect(),
+    }
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::EMPTY
+        | Expression::Ident(_)
+        | Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Str(_)
+        | Expression::Boolean(_)
+        | Expression::Null => expr,
+        Expression::Prefix(op, right) => Expression::Prefix(op, Box::new(folder.fold_expression(*right))),
+        Expression::Infix(op, left, right) => {
+            let left = folder.fold_expression(*left);
+            let right = folder.fold_expression(*right);
+            Expression::Infix(op, Box::new(left), Box::new(right))
+        }
+        Expression::If(condition, consequence, alternative) => {
+            let condition = folder.fold_expression(*condition);
+            let consequence = consequence.map(|block| folder.fold_block(block));
+            let alternative = alternative.map(|block| folder.fold_block(block));
+            Expression::If(Box::new(condition), consequence, alternative)
+        }
+        Expression::Function(params, body) => {
+            let params = params.into_iter().map(|p| folder.fold_identifier(p)).collect();
+            Expression::Function(params, body.map(|block| folder.fold_block(block)))
+        }
+        Expression::Call(function, args) => {
+            let function = folder.fold_expression(*function);
+            let args = args.into_iter().map(|arg| folder.fold_expression(arg)).collect();
+            Expression::Call(Box::new(function), args)
+        }
+        Expression::Array(elements) => {
+            Expression::Array(elements.into_iter().map(|e| folder.fold_expression(e)).collect())
+        }
+        Expression::Hash(pairs) => Expression::Hash(
+            pairs
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = folder.fold_expression(key);
+                    (key, folder.fold_expression(value))
+                })
+                .collect(),
+        ),
+        Expression::Index(left, index) => {
+            let left = folder.fold_expression(*left);
+            let index = folder.fold_expression(*index);
+            Expression::Index(Box::new(left), Box::new(index))
+        }
+        Expression::Assign(op, name, value) => {
+            let name = folder.fold_identifier(name);
+            Expression::Assign(op, name, Box::new(folder.fold_expression(*value)))
+        }
+        Expression::Range(start, end, inclusive) => {
+            let start = folder.fold_expression(*start);
+            let end = folder.fold_expression(*end);
+            Expression::Range(Box::new(start), Box::new(end), inclusive)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_expression, walk_expression_mut, Fold, VisitMut, Visitor};
+    use crate::{
+        ast::{Expression, Identifier, Program},
+        lexer::Lexer,
+        parser::Parser,
+        token::Token,
+    };
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input)).parse_program().unwrap()
+    }
+
+    #[derive(Default)]
+    struct Names {
+        bound: Vec<String>,
+        used: Vec<String>,
+    }
+
+    impl Visitor for Names {
+        fn visit_expression(&mut self, expr: &Expression) {
+            if let Expression::Ident(name) = expr {
+                self.used.push(name.clone());
+            }
+            walk_expression(self, expr)
+        }
+
+        fn visit_identifier(&mut self, identifier: &Identifier) {
+            self.bound.push(identifier.value.clone());
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let program = parse("let f = fn(a, b) { if (a) { [b][0] } else { {c: d}[a](e) } }; f(x, -y);");
+        let mut names = Names::default();
+        names.visit_program(&program);
+        assert_eq!(names.bound, ["f", "a", "b"]);
+        assert_eq!(names.used, ["a", "b", "c", "d", "a", "e", "f", "x", "y"]);
+    }
+
+    struct Rename(&'static str, &'static str);
+
+    impl VisitMut for Rename {
+        fn visit_expression_mut(&mut self, expr: &mut Expression) {
+            match expr {
+                Expression::Ident(name) if name == self.0 => *name = self.1.to_string(),
+                _ => walk_expression_mut(self, expr),
+            }
+        }
+
+        fn visit_identifier_mut(&mut self, identifier: &mut Identifier) {
+            if identifier.value == self.0 {
+                identifier.value = self.1.to_string();
+                identifier.token = Token::Ident(self.1.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let mut program = parse("let x = 1; let f = fn(x) { x + y }; f(x)");
+        Rename("x", "z").visit_program_mut(&mut program);
+        assert_eq!(program, parse("let z = 1; let f = fn(z) { z + y }; f(z)"));
+    }
+
+    // Folds additions of integer literals, innermost first.
+    struct ConstantFold;
+
+    impl Fold for ConstantFold {
+        fn fold_expression(&mut self, expr: Expression) -> Expression {
+            match super::fold_expression(self, expr) {
+                Expression::Infix(Token::Plus, left, right) => match (*left, *right) {
+                    (Expression::Integer(a), Expression::Integer(b)) => Expression::Integer(a + b),
+                    (left, right) => Expression::Infix(Token::Plus, Box::new(left), Box::new(right)),
+                },
+                expr => expr,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let program = parse("let a = 1 + 2 + 3; fn() { [x + (4 + 5)] }; if (1 + 1) { 2 + 2 }");
+        let folded = ConstantFold.fold_program(program);
+        assert_eq!(folded, parse("let a = 6; fn() { [x + 9] }; if (2) { 4 }"));
+    }
+}
@@ -0,0 +1,276 @@
+//! A lossless concrete syntax tree. Unlike the `ast`, it keeps every token
+//! of the source, including whitespace and comments, so printing a tree gives
+//! back the exact input it was parsed from.
+
+use std::fmt;
+
+use crate::{
+    ast,
+    lexer::Lexer,
+    parser::{ParseError, Parser},
+    token::{Span, SpannedToken, Token},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    ReturnStatement,
+    WhileStatement,
+    ForStatement,
+    BreakStatement,
+    ContinueStatement,
+    ExpressionStatement,
+    Block,
+    Name,
+    Literal,
+    Prefix,
+    Infix,
+    Paren,
+    If,
+    Function,
+    Call,
+    Array,
+    Hash,
+    Index,
+    Assign,
+    Range,
+}
+
+/// A token together with its source text. Whitespace and comments are
+/// `Token::Whitespace` and `Token::Comment` trivia tokens.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken {
+    pub token: Token,
+    pub span: Span,
+    pub text: String,
+}
+
+impl SyntaxToken {
+    pub fn is_trivia(&self) -> bool {
+        self.token.is_trivia()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// Trivia between two tokens belongs to the innermost node containing both;
+/// trivia before the first token of a node belongs to its parent.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// All tokens under this node in source order, trivia included.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `input` into a concrete syntax tree and the `ast::Program` for it,
+/// both from the same pass of the parser. The tree is built even if there are
+/// errors; it still covers all of `input`.
+pub fn parse(input: &str) -> (SyntaxNode, Result<ast::Program, Vec<ParseError>>) {
+    let mut parser = Parser::with_cst(Lexer::with_trivia(input));
+    let program = parser.parse_program();
+    let tree = parser.take_cst().unwrap_or_default().finish(input);
+    (tree, program)
+}
+
+/// Collects the parser's tokens and the token ranges of the nodes it
+/// recognized, then assembles them into a tree.
+#[derive(Debug, Default)]
+pub(crate) struct Builder {
+    elements: Vec<SpannedToken>,
+    // Index into `elements` of each non-trivia token
+    token_elements: Vec<usize>,
+    tokens_seen: usize,
+    // Kind and first and last token index, in the order nodes were started
+    nodes: Vec<(SyntaxKind, usize, Option<usize>)>,
+}
+
+impl Builder {
+    pub(crate) fn push_trivia(&mut self, trivia: SpannedToken) {
+        if !self.at_eof() {
+            self.elements.push(trivia);
+        }
+    }
+
+    // The lexer keeps returning `EOF` at the end; only the first one is kept.
+    pub(crate) fn push_token(&mut self, token: SpannedToken) {
+        self.tokens_seen += 1;
+        if !self.at_eof() {
+            self.token_elements.push(self.elements.len());
+            self.elements.push(token);
+        }
+    }
+
+    fn at_eof(&self) -> bool {
+        self.elements.last().is_some_and(|last| last.token == Token::EOF)
+    }
+
+    /// Index of the parser's current token; the parser has also read the one after it.
+    pub(crate) fn current(&self) -> usize {
+        self.tokens_seen.saturating_sub(2)
+    }
+
+    pub(crate) fn start_node(&mut self, kind: SyntaxKind, start: usize) -> usize {
+        self.nodes.push((kind, start, None));
+        self.nodes.len() - 1
+    }
+
+    pub(crate) fn finish_node(&mut self, node: usize, end: usize) {
+        self.nodes[node].2 = Some(end);
+    }
+
+    pub(crate) fn finish(self, input: &str) -> SyntaxNode {
+        let last_token = self.token_elements.len();
+        // Nodes that weren't finished, e.g. because of a parse error, are dropped
+        let mut nodes: Vec<(SyntaxKind, usize, usize)> = self
+            .nodes
+            .iter()
+            .filter_map(|&(kind, start, end)| match end {
+                Some(end) if start <= end && end < last_token => {
+                    Some((kind, self.token_elements[start], self.token_elements[end]))
+                }
+                _ => None,
+            })
+            .collect();
+        // Outer nodes first; the sort is stable so equal ranges keep their order
+        nodes.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)));
+
+        let mut stack = vec![(SyntaxKind::Program, usize::MAX, vec![])];
+        let mut nodes = nodes.into_iter().peekable();
+        for (i, element) in self.elements.into_iter().enumerate() {
+            while stack.last().is_some_and(|&(_, end, _)| end < i) {
+                close(&mut stack);
+            }
+            while let Some(&(kind, start, end)) = nodes.peek() {
+                if start != i {
+                    break;
+                }
+                // Ranges from error recovery may overlap without nesting
+                if stack.last().is_some_and(|&(_, parent_end, _)| end <= parent_end) {
+                    stack.push((kind, end, vec![]));
+                }
+                nodes.next();
+            }
+            let text = input.get(element.span.start..element.span.end).unwrap_or("").to_string();
+            let token = SyntaxToken { token: element.token, span: element.span, text };
+            if let Some((_, _, children)) = stack.last_mut() {
+                children.push(SyntaxElement::Token(token));
+            }
+        }
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+        let (kind, _, children) = stack.pop().unwrap_or((SyntaxKind::Program, 0, vec![]));
+        SyntaxNode { kind, children }
+    }
+}
+
+fn close(stack: &mut Vec<(SyntaxKind, usize, Vec<SyntaxElement>)>) {
+    if let Some((kind, _, children)) = stack.pop() {
+        if let Some((_, _, parent)) = stack.last_mut() {
+            parent.push(SyntaxElement::Node(SyntaxNode { kind, children }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, SyntaxKind, SyntaxNode};
+    use crate::{lexer::Lexer, parser::Parser, token::Token};
+
+    fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.nodes().map(|node| node.kind).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            "",
+            "  \n",
+            "let x = 5; // five\n\n/* a /* nested */ comment */ x * (2 + 3)\t;\r\n",
+            "let add = fn(a, b) {\n  a + b // sum\n};\nadd(1, [2, 3][0]);",
+            "if (x < 1) { \"yes\" } else { {\"a\": 1}[\"a\"] }",
+            "let = 5; @ fn(1) { let y = ; } \"unterminated",
+            "let café = 1; /* never closed",
+        ];
+        for input in inputs {
+            let (tree, program) = parse(input);
+            assert_eq!(tree.to_string(), input);
+            assert_eq!(tree.kind, SyntaxKind::Program);
+            let expected = Parser::new(Lexer::new(input)).parse_program();
+            assert_eq!(program, expected);
+        }
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let (tree, _) = parse("let x = 1 + f(2); // note\nreturn -x;");
+        assert_eq!(kinds(&tree), [SyntaxKind::LetStatement, SyntaxKind::ReturnStatement]);
+
+        let let_statement = tree.nodes().next().unwrap();
+        // The `;` belongs to the statement, the comment after it doesn't
+        assert_eq!(let_statement.to_string(), "let x = 1 + f(2);");
+        assert_eq!(kinds(let_statement), [SyntaxKind::Infix]);
+        let infix = let_statement.nodes().next().unwrap();
+        assert_eq!(kinds(infix), [SyntaxKind::Literal, SyntaxKind::Call]);
+        let call = infix.nodes().nth(1).unwrap();
+        assert_eq!(call.to_string(), "f(2)");
+        assert_eq!(kinds(call), [SyntaxKind::Name, SyntaxKind::Literal]);
+
+        let comments: Vec<&str> = tree
+            .tokens()
+            .into_iter()
+            .filter(|t| matches!(t.token, Token::Comment(_)))
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(comments, ["// note"]);
+    }
+
+    #[test]
+    fn test_trivia_inside_nodes() {
+        let (tree, _) = parse("fn(a) {\n  // body\n  a\n}");
+        let function = tree.nodes().next().unwrap().nodes().next().unwrap();
+        assert_eq!(function.kind, SyntaxKind::Function);
+        let block = function.nodes().next().unwrap();
+        assert_eq!(block.kind, SyntaxKind::Block);
+        assert_eq!(block.to_string(), "{\n  // body\n  a\n}");
+        assert!(block.tokens()[1].is_trivia());
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+
+use super::Evaluator;
+use crate::object::{HashKey, Object};
+
+/// The Rust side of a builtin. The argument count has already been checked
+/// against the builtin's `Arity`.
+pub type NativeFn = dyn Fn(&[Object]) -> Result<Object, Error>;
+
+/// How many arguments a builtin takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn accepts(self, count: usize) -> bool {
+        match self {
+            Arity::Exactly(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
+/// Why a builtin failed. Scripts see it as an error object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// An argument has a type the builtin doesn't take, e.g. `len(1)`.
+    UnsupportedArgument { found: &'static str },
+    /// Any other failure, with the message scripts get.
+    Message(String),
+}
+
+impl Error {
+    pub fn unsupported(arg: &Object) -> Error {
+        Error::UnsupportedArgument { found: arg.type_name() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedArgument { found } => write!(f, "argument not supported, got {}", found),
+            Error::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A named native function, the value of `Object::Builtin`.
+#[derive(Clone)]
+pub struct Builtin {
+    name: Rc<str>,
+    arity: Arity,
+    function: Rc<NativeFn>,
+}
+
+impl Builtin {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    pub fn call(&self, args: &[Object]) -> Object {
+        if !self.arity.accepts(args.len()) {
+            return Object::Error(format!("wrong number of arguments: want={}, got={}", self.arity, args.len()));
+        }
+        match (self.function)(args) {
+            Ok(obj) => obj,
+            Err(Error::UnsupportedArgument { found }) => {
+                Object::Error(format!("argument to `{}` not supported, got {}", self.name, found))
+            }
+            Err(Error::Message(message)) => Object::Error(message),
+        }
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builtin").field("name", &self.name).field("arity", &self.arity).finish_non_exhaustive()
+    }
+}
+
+/// Two builtins are equal if they are the same registered function.
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
+    }
+}
+
+/// The builtins of an evaluator, by name. Identifiers only get here when
+/// they aren't bound in the environment, so scripts can shadow any of them.
+pub(crate) struct Builtins {
+    functions: HashMap<String, Builtin>,
+}
+
+impl Builtins {
+    /// `puts`, `len`, `type`, `str`, `int` and the array and hash functions.
+    pub fn standard() -> Builtins {
+        let mut builtins = Builtins { functions: HashMap::new() };
+        builtins.register("puts", Arity::AtLeast(0), puts);
+        builtins.register("len", Arity::Exactly(1), len);
+        builtins.register("type", Arity::Exactly(1), type_of);
+        builtins.register("str", Arity::Exactly(1), str);
+        builtins.register("int", Arity::Exactly(1), int);
+        builtins.register("first", Arity::Exactly(1), first);
+        builtins.register("last", Arity::Exactly(1), last);
+        builtins.register("rest", Arity::Exactly(1), rest);
+        builtins.register("push", Arity::Exactly(2), push);
+        builtins.register("keys", Arity::Exactly(1), keys);
+        builtins.register("values", Arity::Exactly(1), values);
+        builtins.register("has", Arity::Exactly(2), has);
+        builtins.register("delete", Arity::Exactly(2), delete);
+        builtins
+    }
+
+    /// Adds `function` as `name`, replacing any builtin already called that.
+    pub fn register<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, Error> + 'static,
+    {
+        let builtin = Builtin { name: name.into(), arity, function: Rc::new(function) };
+        self.functions.insert(name.to_string(), builtin);
+    }
+
+    /// The builtin called `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<Object> {
+        self.functions.get(name).map(|builtin| Object::Builtin(builtin.clone()))
+    }
+}
+
+// Each argument on its own line, strings without quotes.
+fn puts(args: &[Object]) -> Result<Object, Error> {
+    for arg in args {
+        println!("{}", Evaluator::unwrap_object(arg.clone()));
+    }
+    Ok(Object::Null)
+}
+
+fn len(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::String(s) => Ok(Object::Integer(s.chars().count() as i64)),
+        Object::Array(elements) => Ok(Object::Integer(elements.len() as i64)),
+        Object::Hash(hash) => Ok(Object::Integer(hash.len() as i64)),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn type_of(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(args[0].type_name().to_string()))
+}
+
+fn str(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(Evaluator::unwrap_object(args[0].clone())))
+}
+
+// Floats are truncated toward zero, strings parsed as decimal integers.
+fn int(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        obj @ (Object::Integer(_) | Object::BigInt(_)) => Ok(obj.clone()),
+        Object::Boolean(x) => Ok(Object::Integer(*x as i64)),
+        Object::Float(x) => match BigInt::from_f64(x.trunc()) {
+            Some(x) => Ok(Object::from_big_int(x)),
+            None => Err(Error::Message(format!("can't convert {} to an integer", x))),
+        },
+        Object::String(s) => match s.trim().parse::<BigInt>() {
+            Ok(x) => Ok(Object::from_big_int(x)),
+            Err(_) => Err(Error::Message(format!("can't convert {:?} to an integer", s))),
+        },
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn first(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.first().cloned().unwrap_or(Object::Null)),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn last(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.last().cloned().unwrap_or(Object::Null)),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn rest(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Array(elements) if elements.is_empty() => Ok(Object::Null),
+        Object::Array(elements) => Ok(Object::Array(elements[1..].to_vec())),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn push(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(Object::Array(elements))
+        }
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn keys(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Array(hash.keys().map(|k| k.to_object()).collect())),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn values(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Array(hash.values().cloned().collect())),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn has(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Boolean(hash.contains_key(&hash_key(&args[1])?))),
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn delete(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::Hash(hash) => {
+            let mut hash = hash.clone();
+            hash.remove(&hash_key(&args[1])?);
+            Ok(Object::Hash(hash))
+        }
+        other => Err(Error::unsupported(other)),
+    }
+}
+
+fn hash_key(key: &Object) -> Result<HashKey, Error> {
+    key.hash_key()
+        .ok_or_else(|| Error::Message(format!("unusable as hash key: {}", key.type_name())))
+}
@@ -1,41 +1,78 @@
 use std::collections::HashMap;
+use std::fmt;
 use crate::object::Object;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Env {
-    pub(crate) store: HashMap<String, Object>,
-    pub(crate) outer: Option<Rc<RefCell<Env>>>,
+#[derive(Default)]
+struct Frame {
+    store: HashMap<String, Object>,
+    outer: Option<Env>,
 }
 
+/// A scope: a shared frame of bindings plus a link to the scope it was
+/// created in. Cloning an `Env` is cheap and gives another handle to the
+/// same frame, so a closure sees `let`s made in its scope after it was
+/// created.
+///
+/// A function stored in the scope it closes over makes a reference cycle,
+/// so such frames are never freed.
+#[derive(Clone, Default)]
+pub struct Env(Rc<RefCell<Frame>>);
+
 impl Env {
     pub fn new() -> Self {
-        Env {
+        Env::default()
+    }
+
+    /// A new, empty scope inside `self`, e.g. for a function call.
+    pub fn enclosed(&self) -> Self {
+        Env(Rc::new(RefCell::new(Frame {
             store: HashMap::new(),
-            outer: None,
-        }
+            outer: Some(self.clone()),
+        })))
     }
 
-    pub fn new_closure(env_func: &Env, env: &Env) -> Self {
-        Env {
-            store: env_func.store.clone(),
-            outer: Some(Rc::new(RefCell::new(env.clone()))),
+    pub fn get(&self, key: &str) -> Option<Object> {
+        let frame = self.0.borrow();
+        match frame.store.get(key) {
+            Some(x) => Some(x.clone()),
+            None => frame.outer.as_ref().and_then(|outer| outer.get(key)),
         }
     }
-    pub fn get(&mut self, key: &str) -> Object {
-        match self.store.get(key) {
-            None => {
-                if let Some(global) = &self.outer {
-                    global.borrow_mut().get(key)
-                } else {
-                    Object::Null
-                }
-            }
-            Some(x) => x.clone()
+
+    pub fn set(&self, key: &str, value: Object) {
+        self.0.borrow_mut().store.insert(key.to_string(), value);
+    }
+
+    /// Updates the binding `get` would find. Returns false, and changes
+    /// nothing, if `key` isn't bound in this scope or an outer one.
+    pub fn assign(&self, key: &str, value: Object) -> bool {
+        let mut frame = self.0.borrow_mut();
+        if let Some(slot) = frame.store.get_mut(key) {
+            *slot = value;
+            return true;
+        }
+        match &frame.outer {
+            Some(outer) => outer.assign(key, value),
+            None => false,
         }
     }
-    pub fn set(&mut self, key: &str, value: Object) {
-        self.store.insert(key.to_string(), value);
+}
+
+// Only the names: values may hold functions that point back at this frame.
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frame = self.0.borrow();
+        let mut names: Vec<&String> = frame.store.keys().collect();
+        names.sort();
+        f.debug_struct("Env").field("names", &names).finish_non_exhaustive()
     }
-}
\ No newline at end of file
+}
+
+/// Two handles are equal if they are the same scope.
+impl PartialEq for Env {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -1,22 +1,69 @@
 pub mod env;
+pub mod builtins;
 
 use crate::ast::{self, Statement, Expression};
 use crate::object::{self, Object};
 use crate::token::Token;
+use builtins::{Arity, Builtins};
 use ast::BlockStatement;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use num_bigint::{BigInt, Sign};
+use num_traits::{ToPrimitive, Zero};
+
+// `**` and `<<` refuse to build integers larger than this
+const MAX_BIG_INT_BITS: u64 = 1 << 26;
+
+/// What integer arithmetic does when the result doesn't fit in an i64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Continue with arbitrary-precision integers.
+    Promote,
+    /// Raise a runtime error.
+    Error,
+    /// Wrap around in two's complement.
+    Wrap,
+}
 
 pub struct Evaluator {
-    env: env::Env
+    env: env::Env,
+    overflow: Overflow,
+    builtins: Builtins,
 }
 
 impl Evaluator {
     pub fn new() -> Evaluator {
+        Evaluator::with_overflow(Overflow::Promote)
+    }
+
+    pub fn with_overflow(overflow: Overflow) -> Evaluator {
         Evaluator {
-            env: env::Env::new()
+            env: env::Env::new(),
+            overflow,
+            builtins: Builtins::standard(),
         }
     }
 
+    /// Makes `function` callable from scripts as `name`, replacing the
+    /// builtin of that name if there is one. Calls with an argument count
+    /// `arity` doesn't allow fail before `function` runs.
+    ///
+    /// ```
+    /// use monkey_lang_rs::evaluator::{builtins::{Arity, Error}, Evaluator};
+    /// use monkey_lang_rs::object::Object;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.register_builtin("double", Arity::Exactly(1), |args| match &args[0] {
+    ///     Object::Integer(x) => Ok(Object::Integer(x * 2)),
+    ///     other => Err(Error::unsupported(other)),
+    /// });
+    /// ```
+    pub fn register_builtin<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, builtins::Error> + 'static,
+    {
+        self.builtins.register(name, arity, function);
+    }
+
     fn is_truthy(obj: &Object) -> bool {
         match obj {
             Object::Null | Object::Boolean(false) => false,
@@ -24,17 +71,138 @@ impl Evaluator {
         }
     }
 
-    fn eval_int_infix(op: &Token, a: i32, b: i32) -> Object {
+    // `checked` gives `None` on overflow, what happens then depends on the
+    // overflow mode: `wrapping` is only used in `Overflow::Wrap`.
+    fn int_arithmetic(
+        &self,
+        op: &Token,
+        a: i64,
+        b: i64,
+        checked: fn(i64, i64) -> Option<i64>,
+        wrapping: fn(i64, i64) -> i64,
+    ) -> Object {
+        match (checked(a, b), self.overflow) {
+            (Some(x), _) => Object::Integer(x),
+            (None, Overflow::Promote) => Evaluator::eval_big_int_infix(op, BigInt::from(a), BigInt::from(b)),
+            (None, Overflow::Wrap) => Object::Integer(wrapping(a, b)),
+            (None, Overflow::Error) => Object::Error(format!("integer overflow: {} {} {}", a, op, b)),
+        }
+    }
+
+    fn eval_int_infix(&self, op: &Token, a: i64, b: i64) -> Object {
         match op {
-            Token::Plus => { Object::Integer(a + b) }
-            Token::Minus => { Object::Integer(a - b) }
-            Token::Asterisk => { Object::Integer(a * b) }
-            Token::Slash => { Object::Integer(a / b) }
+            Token::Plus => { self.int_arithmetic(op, a, b, i64::checked_add, i64::wrapping_add) }
+            Token::Minus => { self.int_arithmetic(op, a, b, i64::checked_sub, i64::wrapping_sub) }
+            Token::Asterisk => { self.int_arithmetic(op, a, b, i64::checked_mul, i64::wrapping_mul) }
+            Token::Slash if b == 0 => { Object::Error("division by zero".to_string()) }
+            Token::Slash => { self.int_arithmetic(op, a, b, i64::checked_div, i64::wrapping_div) }
+            Token::Percent if b == 0 => { Object::Error("division by zero".to_string()) }
+            Token::Percent => { self.int_arithmetic(op, a, b, i64::checked_rem, i64::wrapping_rem) }
+            // `0 ** -n` is `1 / 0 ** n`
+            Token::Power if a == 0 && b < 0 => { Object::Error("division by zero".to_string()) }
+            Token::Power if b < 0 => { Object::Float((a as f64).powf(b as f64)) }
+            Token::Power => { self.int_arithmetic(op, a, b, checked_pow, wrapping_pow) }
+            Token::Shl | Token::Shr if b < 0 => {
+                Object::Error(format!("negative shift amount: {} {} {}", a, op, b))
+            }
+            Token::Shl => { self.int_arithmetic(op, a, b, checked_shl, |a, b| if b < 64 { a << b } else { 0 }) }
+            Token::Shr => { Object::Integer(a >> b.min(63)) }
+            Token::Ampersand => { Object::Integer(a & b) }
+            Token::Pipe => { Object::Integer(a | b) }
+            Token::Caret => { Object::Integer(a ^ b) }
             Token::Lt => { Object::Boolean(a < b) }
             Token::Gt => { Object::Boolean(a > b) }
+            Token::LtEq => { Object::Boolean(a <= b) }
+            Token::GtEq => { Object::Boolean(a >= b) }
             Token::Eq => { Object::Boolean(a == b) }
             Token::NotEq => { Object::Boolean(a != b) }
-            _ => Object::Null
+            _ => Object::Error(format!("unknown operator: INTEGER {} INTEGER", op))
+        }
+    }
+
+    fn eval_big_int_infix(op: &Token, a: BigInt, b: BigInt) -> Object {
+        match op {
+            Token::Plus => { Object::from_big_int(a + b) }
+            Token::Minus => { Object::from_big_int(a - b) }
+            Token::Asterisk => { Object::from_big_int(a * b) }
+            Token::Slash if b.sign() == Sign::NoSign => { Object::Error("division by zero".to_string()) }
+            Token::Slash => { Object::from_big_int(a / b) }
+            Token::Percent if b.sign() == Sign::NoSign => { Object::Error("division by zero".to_string()) }
+            Token::Percent => { Object::from_big_int(a % b) }
+            Token::Power => { Evaluator::big_int_pow(a, b) }
+            Token::Shl | Token::Shr if b.sign() == Sign::Minus => {
+                Object::Error(format!("negative shift amount: {} {} {}", a, op, b))
+            }
+            Token::Shl => match b.to_u64() {
+                _ if a.is_zero() => Object::Integer(0),
+                Some(n) if a.bits() + n <= MAX_BIG_INT_BITS => Object::from_big_int(a << n),
+                _ => Object::Error(format!("integer too large: {} {} {}", a, op, b)),
+            },
+            // Shifting right rounds down, so everything shifted out leaves 0 or -1
+            Token::Shr => match b.to_u64() {
+                Some(n) if n < a.bits() => Object::from_big_int(a >> n),
+                _ if a.sign() == Sign::Minus => Object::Integer(-1),
+                _ => Object::Integer(0),
+            },
+            Token::Ampersand => { Object::from_big_int(a & b) }
+            Token::Pipe => { Object::from_big_int(a | b) }
+            Token::Caret => { Object::from_big_int(a ^ b) }
+            Token::Lt => { Object::Boolean(a < b) }
+            Token::Gt => { Object::Boolean(a > b) }
+            Token::LtEq => { Object::Boolean(a <= b) }
+            Token::GtEq => { Object::Boolean(a >= b) }
+            Token::Eq => { Object::Boolean(a == b) }
+            Token::NotEq => { Object::Boolean(a != b) }
+            _ => Object::Error(format!("unknown operator: INTEGER {} INTEGER", op))
+        }
+    }
+
+    // Negative exponents give a float, like they do for small integers.
+    fn big_int_pow(a: BigInt, b: BigInt) -> Object {
+        if b.sign() == Sign::Minus {
+            if a.is_zero() {
+                return Object::Error("division by zero".to_string());
+            }
+            return match (a.to_f64(), b.to_f64()) {
+                (Some(a), Some(b)) => Object::Float(a.powf(b)),
+                _ => Object::Float(0.0),
+            };
+        }
+        match b.to_u32() {
+            Some(e) if a.bits() * u64::from(e) <= MAX_BIG_INT_BITS => Object::from_big_int(a.pow(e)),
+            // 0, 1 and -1 only depend on whether the exponent is odd
+            _ if a.bits() <= 1 => Object::from_big_int(a.pow(if (b % 2u32).is_zero() { 2u32 } else { 3 })),
+            _ => Object::Error(format!("integer too large: {} ** {}", a, b)),
+        }
+    }
+
+    fn eval_float_infix(op: &Token, a: f64, b: f64) -> Object {
+        match op {
+            Token::Plus => { Object::Float(a + b) }
+            Token::Minus => { Object::Float(a - b) }
+            Token::Asterisk => { Object::Float(a * b) }
+            Token::Slash if b == 0.0 => { Object::Error("division by zero".to_string()) }
+            Token::Slash => { Object::Float(a / b) }
+            Token::Percent if b == 0.0 => { Object::Error("division by zero".to_string()) }
+            Token::Percent => { Object::Float(a % b) }
+            Token::Power if a == 0.0 && b < 0.0 => { Object::Error("division by zero".to_string()) }
+            Token::Power => { Object::Float(a.powf(b)) }
+            Token::Lt => { Object::Boolean(a < b) }
+            Token::Gt => { Object::Boolean(a > b) }
+            Token::LtEq => { Object::Boolean(a <= b) }
+            Token::GtEq => { Object::Boolean(a >= b) }
+            Token::Eq => { Object::Boolean(a == b) }
+            Token::NotEq => { Object::Boolean(a != b) }
+            _ => Object::Error(format!("unknown operator: FLOAT {} FLOAT", op))
+        }
+    }
+
+    fn to_f64(obj: &Object) -> Option<f64> {
+        match obj {
+            Object::Integer(x) => Some(*x as f64),
+            Object::BigInt(x) => x.to_f64(),
+            Object::Float(x) => Some(*x),
+            _ => None
         }
     }
 
@@ -43,37 +211,159 @@ impl Evaluator {
         for s in block {
             res = self.eval_statement(&s);
             match &res {
-                Object::Return(x) => return res,
+                Object::Return(_) | Object::Error(_) | Object::Break | Object::Continue => return res,
                 _ => {}
             }
         }
         res
     }
 
+    // Evaluates left to right, stopping at the first error or signal.
+    fn eval_exprs(&mut self, exprs: &[ast::Expression]) -> Result<Vec<Object>, Object> {
+        let mut objects = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            let obj = self.eval_expr(expr);
+            if obj.is_error() || obj.is_signal() {
+                return Err(obj);
+            }
+            objects.push(obj);
+        }
+        Ok(objects)
+    }
+
+    fn apply_function(&mut self, function: Object, args: Vec<Object>) -> Object {
+        match function {
+            Object::Builtin(builtin) => builtin.call(&args),
+            Object::Function(params, body, env) => {
+                if params.len() != args.len() {
+                    return Object::Error(format!(
+                        "wrong number of arguments: want={}, got={}", params.len(), args.len()
+                    ));
+                }
+                // Calls run in a fresh scope inside the one the function was defined in
+                let closure_env = env.enclosed();
+                for (value, name) in args.into_iter().zip(params) {
+                    closure_env.set(&name.value, value);
+                }
+                let env_before_closure_exec = std::mem::replace(&mut self.env, closure_env);
+                let return_object = self.eval_block_statements(&body.statements);
+                let ret = match return_object {
+                    Object::Return(x) => *x,
+                    Object::Break | Object::Continue => Self::outside_loop(&return_object),
+                    o => o
+                };
+                self.env = env_before_closure_exec;
+                ret
+            }
+            other => Object::Error(format!("not a function: {}", other.type_name()))
+        }
+    }
+
+    fn eval_prefix(&self, tok: &Token, right: Object) -> Object {
+        match tok {
+            Token::Minus => {
+                match right {
+                    Object::Integer(x) => {
+                        match (x.checked_neg(), self.overflow) {
+                            (Some(x), _) => Object::Integer(x),
+                            (None, Overflow::Promote) => Object::from_big_int(-BigInt::from(x)),
+                            (None, Overflow::Wrap) => Object::Integer(x.wrapping_neg()),
+                            (None, Overflow::Error) => Object::Error(format!("integer overflow: -({})", x)),
+                        }
+                    }
+                    Object::BigInt(x) => { Object::from_big_int(-x) }
+                    Object::Float(x) => { Object::Float(-x) }
+                    _ => Object::Error(format!("unknown operator: -{}", right.type_name()))
+                }
+            }
+            Token::Plus => match right {
+                Object::Integer(_) | Object::BigInt(_) | Object::Float(_) => right,
+                _ => Object::Error(format!("unknown operator: +{}", right.type_name())),
+            },
+            Token::Tilde => match right {
+                Object::Integer(x) => Object::Integer(!x),
+                Object::BigInt(x) => Object::from_big_int(!x),
+                _ => Object::Error(format!("unknown operator: ~{}", right.type_name())),
+            },
+            Token::Bang => { Object::Boolean(!Evaluator::is_truthy(&right)) }
+            _ => Object::Error(format!("unknown operator: {}{}", tok, right.type_name()))
+        }
+    }
+
+    fn eval_infix(&self, op: &Token, left: Object, right: Object) -> Object {
+        // A float on either side makes it float arithmetic
+        if let (Object::Float(_), _) | (_, Object::Float(_)) = (&left, &right) {
+            if let (Some(a), Some(b)) = (Evaluator::to_f64(&left), Evaluator::to_f64(&right)) {
+                return Evaluator::eval_float_infix(op, a, b);
+            }
+        }
+        match (left, right) {
+            (Object::Integer(a), Object::Integer(b)) => {
+                self.eval_int_infix(op, a, b)
+            }
+            (Object::BigInt(a), Object::BigInt(b)) => {
+                Evaluator::eval_big_int_infix(op, a, b)
+            }
+            (Object::BigInt(a), Object::Integer(b)) => {
+                Evaluator::eval_big_int_infix(op, a, BigInt::from(b))
+            }
+            (Object::Integer(a), Object::BigInt(b)) => {
+                Evaluator::eval_big_int_infix(op, BigInt::from(a), b)
+            }
+            (Object::String(a), Object::String(b)) => {
+                match op {
+                    Token::Plus => { Object::String(a + &b) }
+                    Token::Eq => { Object::Boolean(a == b) }
+                    Token::NotEq => { Object::Boolean(a != b) }
+                    _ => Object::Error(format!("unknown operator: STRING {} STRING", op))
+                }
+            }
+            (left, right) => {
+                match op {
+                    Token::Eq => { Object::Boolean(left == right) }
+                    Token::NotEq => { Object::Boolean(left != right) }
+                    _ if left.type_name() != right.type_name() => Object::Error(format!(
+                        "type mismatch: {} {} {}", left.type_name(), op, right.type_name()
+                    )),
+                    _ => Object::Error(format!(
+                        "unknown operator: {} {} {}", left.type_name(), op, right.type_name()
+                    ))
+                }
+            }
+        }
+    }
+
+    fn eval_index(left: Object, index: Object) -> Object {
+        match (left, index) {
+            (Object::Array(elements), Object::Integer(i)) => {
+                // Out of range, including negative, indexes give null
+                usize::try_from(i).ok()
+                    .and_then(|i| elements.get(i).cloned())
+                    .unwrap_or(Object::Null)
+            }
+            (Object::Array(_), Object::BigInt(_)) => Object::Null,
+            (Object::Hash(hash), key) => {
+                match key.to_hash_key() {
+                    Ok(key) => hash.get(&key).cloned().unwrap_or(Object::Null),
+                    Err(err) => err,
+                }
+            }
+            (left, index) => Object::Error(format!(
+                "index operator not supported: {}[{}]", left.type_name(), index.type_name()
+            ))
+        }
+    }
 
     fn eval_expr(&mut self, expr: &ast::Expression) -> object::Object {
         match expr {
             Expression::Call(ex, args) => {
-                if let Object::Function(params, body, env) = self.eval_expr(ex) {
-                    let args = args.iter().map(
-                        |x| self.eval_expr(x)
-                    ).zip(params).collect::<Vec<_>>();
-                    let mut closure_env = self::env::Env::new_closure(&env, &self.env);
-                    let env_before_closure_exec = self.env.clone();
-                    for (value, name) in args {
-                        closure_env.set(&name.value, value);
-                    }
-                    self.env = closure_env;
-                    let return_object = self.eval_block_statements(&body.statements);
-                    let ret = match return_object {
-                        Object::Return(x) => *x,
-                        o => o
-                    };
-                    self.env = env_before_closure_exec;
-                    ret
-                } else {
-                    println!("Something went wrong!");
-                    Object::Null
+                let function = self.eval_expr(ex);
+                if function.is_error() || function.is_signal() {
+                    return function;
+                }
+                match self.eval_exprs(args) {
+                    Ok(args) => self.apply_function(function, args),
+                    Err(err) => err
                 }
             }
             Expression::Function(args, body) => {
@@ -85,10 +375,50 @@ impl Evaluator {
             }
             Expression::Ident(s) => {
                 let obj = self.env.get(s);
-                obj
+                obj.or_else(|| self.builtins.get(s))
+                    .unwrap_or_else(|| Object::Error(format!("identifier not found: {}", s)))
+            }
+            Expression::Array(elements) => {
+                match self.eval_exprs(elements) {
+                    Ok(elements) => Object::Array(elements),
+                    Err(err) => err
+                }
+            }
+            Expression::Hash(pairs) => {
+                let mut hash = BTreeMap::new();
+                for (key, value) in pairs {
+                    let key = self.eval_expr(key);
+                    if key.is_error() || key.is_signal() {
+                        return key;
+                    }
+                    let key = match key.to_hash_key() {
+                        Ok(key) => key,
+                        Err(err) => return err,
+                    };
+                    let value = self.eval_expr(value);
+                    if value.is_error() || value.is_signal() {
+                        return value;
+                    }
+                    hash.insert(key, value);
+                }
+                Object::Hash(hash)
+            }
+            Expression::Index(left, index) => {
+                let left_o = self.eval_expr(left);
+                if left_o.is_error() || left_o.is_signal() {
+                    return left_o;
+                }
+                let index_o = self.eval_expr(index);
+                if index_o.is_error() || index_o.is_signal() {
+                    return index_o;
+                }
+                Evaluator::eval_index(left_o, index_o)
             }
             Expression::If(condition, consequence, alternative) => {
                 let c = self.eval_expr(condition);
+                if c.is_error() || c.is_signal() {
+                    return c;
+                }
                 if Evaluator::is_truthy(&c) {
                     if let Some(block) = consequence {
                         self.eval_block_statements(&block.statements)
@@ -104,41 +434,91 @@ impl Evaluator {
                 }
             }
             Expression::Integer(x) => Object::Integer(*x),
+            Expression::Float(x) => Object::Float(*x),
+            Expression::Str(x) => Object::String(x.clone()),
             Expression::Boolean(x) => Object::Boolean(*x),
             Expression::Prefix(tok, exp) => {
                 let right = self.eval_expr(exp);
-                match tok {
-                    Token::Minus => {
-                        match right {
-                            Object::Integer(x) => { Object::Integer(-x) }
-                            _ => Object::Null
-                        }
-                    }
-                    Token::Bang => {
-                        match right {
-                            Object::Boolean(x) => { Object::Boolean(!x) }
-                            _ => Object::Null
-                        }
-                    }
-                    _ => Object::Null
+                if right.is_error() || right.is_signal() {
+                    return right;
                 }
+                self.eval_prefix(tok, right)
             }
             Expression::Infix(op, left, right) => {
                 let left_o = self.eval_expr(left);
-                let right_o = self.eval_expr(right);
-                match (left_o, right_o) {
-                    (Object::Integer(a), Object::Integer(b)) => {
-                        Evaluator::eval_int_infix(op, a, b)
-                    }
-                    (Object::Boolean(a), Object::Boolean(b)) => {
-                        match op {
-                            Token::Eq => { Object::Boolean(a == b) }
-                            Token::NotEq => { Object::Boolean(a != b) }
-                            _ => Object::Null
+                if left_o.is_error() || left_o.is_signal() {
+                    return left_o;
+                }
+                // `&&` and `||` only evaluate the right side if the left doesn't decide
+                match (op, Self::is_truthy(&left_o)) {
+                    (Token::And, false) => return Object::Boolean(false),
+                    (Token::Or, true) => return Object::Boolean(true),
+                    (Token::And, true) | (Token::Or, false) => {
+                        let right_o = self.eval_expr(right);
+                        if right_o.is_error() || right_o.is_signal() {
+                            return right_o;
                         }
+                        return Object::Boolean(Self::is_truthy(&right_o));
+                    }
+                    _ => {}
+                }
+                let right_o = self.eval_expr(right);
+                if right_o.is_error() || right_o.is_signal() {
+                    return right_o;
+                }
+                self.eval_infix(op, left_o, right_o)
+            }
+            Expression::Range(start, end, inclusive) => {
+                let start = self.eval_expr(start);
+                if start.is_error() || start.is_signal() {
+                    return start;
+                }
+                let end = self.eval_expr(end);
+                if end.is_error() || end.is_signal() {
+                    return end;
+                }
+                match (start, end) {
+                    (Object::Integer(start), Object::Integer(end)) => Object::Range(start, end, *inclusive),
+                    // Ranges are i64 based, `type_name` calls a BigInt an INTEGER too
+                    (bound @ Object::BigInt(_), _) | (_, bound @ Object::BigInt(_)) => {
+                        Object::Error(format!("range bound out of range: {}", Self::unwrap_object(bound)))
                     }
-                    _ => Object::Null
+                    (start, end) => Object::Error(format!(
+                        "range bounds must be integers: {}{}{}",
+                        start.type_name(),
+                        if *inclusive { "..=" } else { ".." },
+                        end.type_name()
+                    )),
+                }
+            }
+            Expression::Assign(op, name, value) => {
+                let value = self.eval_expr(value);
+                if value.is_error() || value.is_signal() {
+                    return value;
+                }
+                let undefined = || Object::Error(format!("assignment to undefined variable: {}", name.value));
+                // `x += v` is `x = x + v`
+                let operator = match op {
+                    Token::PlusAssign => Some(Token::Plus),
+                    Token::MinusAssign => Some(Token::Minus),
+                    Token::AsteriskAssign => Some(Token::Asterisk),
+                    Token::SlashAssign => Some(Token::Slash),
+                    _ => None,
+                };
+                let value = match operator {
+                    Some(operator) => match self.env.get(&name.value) {
+                        Some(current) => self.eval_infix(&operator, current, value),
+                        None => return undefined(),
+                    },
+                    None => value,
+                };
+                if value.is_error() || value.is_signal() {
+                    return value;
+                }
+                if !self.env.assign(&name.value, value.clone()) {
+                    return undefined();
                 }
+                value
             }
             _ => Object::Null
         }
@@ -148,16 +528,105 @@ impl Evaluator {
         match expr {
             Statement::LetStatement(x, y) => {
                 let value = self.eval_expr(y);
+                if value.is_error() || value.is_signal() {
+                    return value;
+                }
                 self.env.set(&x.value, value.clone());
                 value
             }
             Statement::ReturnStatement(val) => {
-                Object::Return(Box::new(self.eval_expr(val)))
+                let value = self.eval_expr(val);
+                if value.is_error() || value.is_signal() {
+                    return value;
+                }
+                Object::Return(Box::new(value))
             }
             Statement::ExpressionStatement(expr) => {
                 self.eval_expr(expr)
             }
+            Statement::WhileStatement(condition, body) => self.eval_while(condition, body),
+            Statement::ForStatement(name, value, iterable, body) => self.eval_for(name, value, iterable, body),
+            Statement::BreakStatement => Object::Break,
+            Statement::ContinueStatement => Object::Continue,
+        }
+    }
+
+    fn eval_while(&mut self, condition: &ast::Expression, body: &ast::BlockStatement) -> Object {
+        loop {
+            let c = self.eval_expr(condition);
+            if c.is_error() || c.is_signal() {
+                return c;
+            }
+            if !Self::is_truthy(&c) {
+                return Object::Null;
+            }
+            if let Some(res) = self.eval_loop_body(body) {
+                return res;
+            }
+        }
+    }
+
+    // With one name an array, string or range gives its elements and a hash
+    // its keys. With two the first is the index, or for a hash the key, and
+    // the second the element or value.
+    fn eval_for(
+        &mut self,
+        name: &ast::Identifier,
+        value: &Option<ast::Identifier>,
+        iterable: &ast::Expression,
+        body: &ast::BlockStatement,
+    ) -> Object {
+        let iterable = self.eval_expr(iterable);
+        if iterable.is_error() || iterable.is_signal() {
+            return iterable;
+        }
+        let hash = matches!(iterable, Object::Hash(_));
+        let items: Box<dyn Iterator<Item = (Object, Object)>> = match iterable {
+            Object::Array(elements) => Box::new(elements.into_iter().enumerate().map(indexed)),
+            Object::String(s) => {
+                let chars: Vec<Object> = s.chars().map(|c| Object::String(c.to_string())).collect();
+                Box::new(chars.into_iter().enumerate().map(indexed))
+            }
+            Object::Hash(pairs) => Box::new(pairs.into_iter().map(|(k, v)| (k.to_object(), v))),
+            Object::Range(start, end, true) => Box::new((start..=end).map(Object::Integer).enumerate().map(indexed)),
+            Object::Range(start, end, false) => Box::new((start..end).map(Object::Integer).enumerate().map(indexed)),
+            other => return Object::Error(format!("not iterable: {}", other.type_name())),
+        };
+        for (key, item) in items {
+            // Each iteration gets its own scope, so the names don't touch outer
+            // bindings and closures made in the body keep their own values
+            let iteration_env = self.env.enclosed();
+            match value {
+                Some(value) => {
+                    iteration_env.set(&name.value, key);
+                    iteration_env.set(&value.value, item);
+                }
+                None => iteration_env.set(&name.value, if hash { key } else { item }),
+            }
+            let env_before_iteration = std::mem::replace(&mut self.env, iteration_env);
+            let res = self.eval_loop_body(body);
+            self.env = env_before_iteration;
+            if let Some(res) = res {
+                return res;
+            }
         }
+        Object::Null
+    }
+
+    // Runs a loop body once. `Some` ends the loop with that result.
+    fn eval_loop_body(&mut self, body: &ast::BlockStatement) -> Option<Object> {
+        match self.eval_block_statements(&body.statements) {
+            Object::Break => Some(Object::Null),
+            res @ (Object::Return(_) | Object::Error(_)) => Some(res),
+            _ => None,
+        }
+    }
+
+    // A `break` or `continue` that reached a function or the program without
+    // meeting a loop.
+    fn outside_loop(signal: &Object) -> Object {
+        let keyword = if *signal == Object::Break { "break" } else { "continue" };
+        Object::Error(format!("{} outside of a loop", keyword))
     }
 
     pub fn eval_statements(&mut self, statements: &Vec<Statement>) -> Object {
@@ -168,6 +637,8 @@ impl Evaluator {
                 Object::Return(x) => {
                     return *x;
                 }
+                Object::Error(_) => return res,
+                Object::Break | Object::Continue => return Self::outside_loop(&res),
                 _ => {}
             }
         }
@@ -177,14 +648,66 @@ impl Evaluator {
     pub fn unwrap_object(obj: Object) -> String {
         match obj {
             Object::Integer(x) => { x.to_string() }
+            Object::BigInt(x) => { x.to_string() }
+            Object::Float(x) => { format!("{:?}", x) }
             Object::String(x) => { x.to_string() }
             Object::Null => { "null".to_string() }
             Object::Boolean(x) => { x.to_string() }
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.into_iter().map(Evaluator::unwrap_object).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Object::Hash(hash) => {
+                let pairs: Vec<String> = hash.into_iter()
+                    .map(|(k, v)| format!("{}: {}", Evaluator::unwrap_object(k.to_object()), Evaluator::unwrap_object(v)))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            Object::Range(start, end, inclusive) => {
+                format!("{}{}{}", start, if inclusive { "..=" } else { ".." }, end)
+            }
+            // The body is left out, it can be any length
+            Object::Function(params, ..) => {
+                let params: Vec<&str> = params.iter().map(|p| p.value.as_str()).collect();
+                format!("fn({}) {{...}}", params.join(", "))
+            }
+            Object::Builtin(builtin) => format!("builtin {}", builtin.name()),
+            Object::Error(x) => { format!("ERROR: {}", x) }
             _ => "Can't unwrap this type of object!".to_string()
         }
     }
 }
 
+fn indexed((i, item): (usize, Object)) -> (Object, Object) {
+    (Object::Integer(i as i64), item)
+}
+
+fn checked_pow(a: i64, b: i64) -> Option<i64> {
+    u32::try_from(b).ok().and_then(|b| a.checked_pow(b))
+}
+
+// `a ** b` modulo 2^64, by repeated squaring
+fn wrapping_pow(mut base: i64, mut exp: i64) -> i64 {
+    let mut result: i64 = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
+    }
+    result
+}
+
+// Shifting out any bit that differs from the sign counts as overflow.
+fn checked_shl(a: i64, b: i64) -> Option<i64> {
+    if b >= 64 {
+        return (a == 0).then_some(0);
+    }
+    let shifted = a << b;
+    (shifted >> b == a).then_some(shifted)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::lexer;
@@ -226,6 +749,7 @@ mod tests {
             ("let add = fn(x, y) { x + y; }; add(5, 5);", Object::Integer(10)),
             ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", Object::Integer(20)),
             ("fn(x) { x; }(5)", Object::Integer(5)),
+            ("let five = fn() { 5 }; five();", Object::Integer(5)),
         ];
         for test in tests.iter() {
             let l1 = lexer::Lexer::new(test.0);
@@ -365,4 +889,567 @@ mod tests {
             assert_eq!(evaluator.eval_statements(&program1.statements), test.1)
         }
     }
+
+    #[test]
+    fn test_string_expr() {
+        let tests = vec![
+            (r#""Hello World!""#, Object::String("Hello World!".to_string())),
+            (r#""Hello" + " " + "World!""#, Object::String("Hello World!".to_string())),
+            (r#"let greet = fn(name) { "Hi, " + name }; greet("Monkey")"#, Object::String("Hi, Monkey".to_string())),
+            (r#""a" == "a""#, Object::Boolean(true)),
+            (r#""a" == "b""#, Object::Boolean(false)),
+            (r#""a" != "b""#, Object::Boolean(true)),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), test.1)
+        }
+    }
+
+    #[test]
+    fn test_arrays() {
+        let int_array = |xs: &[i64]| Object::Array(xs.iter().map(|x| Object::Integer(*x)).collect());
+        let tests = vec![
+            ("[1, 2 * 2, 3 + 3]", int_array(&[1, 4, 6])),
+            ("[1, 2, 3][0]", Object::Integer(1)),
+            ("[1, 2, 3][2]", Object::Integer(3)),
+            ("let i = 0; [1][i];", Object::Integer(1)),
+            ("[1, 2, 3][1 + 1];", Object::Integer(3)),
+            ("let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];", Object::Integer(6)),
+            ("[1, 2, 3][3]", Object::Null),
+            ("[1, 2, 3][-1]", Object::Null),
+            ("[][0]", Object::Null),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_array_builtins() {
+        let int_array = |xs: &[i64]| Object::Array(xs.iter().map(|x| Object::Integer(*x)).collect());
+        let tests = vec![
+            (r#"len("")"#, Object::Integer(0)),
+            (r#"len("four")"#, Object::Integer(4)),
+            (r#"len("héllo")"#, Object::Integer(5)),
+            ("len([1, 2, 3])", Object::Integer(3)),
+            ("len([])", Object::Integer(0)),
+            ("first([1, 2, 3])", Object::Integer(1)),
+            ("first([])", Object::Null),
+            ("last([1, 2, 3])", Object::Integer(3)),
+            ("last([])", Object::Null),
+            ("rest([1, 2, 3])", int_array(&[2, 3])),
+            ("rest(rest(rest([1, 2, 3])))", int_array(&[])),
+            ("rest([])", Object::Null),
+            ("push([], 1)", int_array(&[1])),
+            ("let a = [1]; let b = push(a, 2); a", int_array(&[1])),
+            ("let len = fn(x) { 42 }; len([1])", Object::Integer(42)),
+            ("let map = fn(arr, f) {
+                let iter = fn(arr, acc) {
+                    if (len(arr) == 0) { acc } else { iter(rest(arr), push(acc, f(first(arr)))) }
+                };
+                iter(arr, [])
+              };
+              map([1, 2, 3], fn(x) { x * 2 })", int_array(&[2, 4, 6])),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_conversion_builtins() {
+        let tests = vec![
+            (r#"type(1) + type(2.5) + type("") + type([]) + type({}) + type(if (false) { 1 })"#, "INTEGERFLOATSTRINGARRAYHASHNULL"),
+            ("[type(true), type(fn(x) { x }), type(len), type(1..2), type(2 ** 100)]",
+             "[BOOLEAN, FUNCTION, BUILTIN, RANGE, INTEGER]"),
+            (r#"str(12) + str(2.5) + str("s") + str([1, "a"])"#, "122.5s[1, a]"),
+            ("str(len)", "builtin len"),
+            ("let f = str; str(f)", "builtin str"),
+            ("str(fn(x) { x })", "fn(x) {...}"),
+            ("let add = fn(a, b) { a + b }; str(add) + str(fn() { 1 })", "fn(a, b) {...}fn() {...}"),
+            (r#"int(" 42 ") + int(2.9) + int(-2.9) + int(true)"#, "43"),
+            (r#"int("123456789012345678901234567890")"#, "123456789012345678901234567890"),
+            ("int(1e20)", "100000000000000000000"),
+            (r#"int("4x")"#, "ERROR: can't convert \"4x\" to an integer"),
+            ("int(1e308 * 10)", "ERROR: can't convert inf to an integer"),
+            ("int([])", "ERROR: argument to `int` not supported, got ARRAY"),
+            ("puts(1, \"two\", [3])", "null"),
+            ("puts()", "null"),
+            ("str()", "ERROR: wrong number of arguments: want=1, got=0"),
+            ("let type = fn(x) { 0 }; type(1)", "0"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_host_builtins() {
+        use super::builtins::{Arity, Error};
+        use std::{cell::RefCell, rc::Rc};
+
+        let printed = Rc::new(RefCell::new(vec![]));
+        let mut evaluator = evaluator::Evaluator::new();
+        let sink = printed.clone();
+        evaluator.register_builtin("puts", Arity::AtLeast(0), move |args| {
+            sink.borrow_mut().extend(args.iter().map(|arg| evaluator::Evaluator::unwrap_object(arg.clone())));
+            Ok(Object::Null)
+        });
+        evaluator.register_builtin("sum", Arity::AtLeast(1), |args| {
+            let mut sum = 0;
+            for arg in args {
+                match arg {
+                    Object::Integer(x) => sum += x,
+                    other => return Err(Error::unsupported(other)),
+                }
+            }
+            Ok(Object::Integer(sum))
+        });
+        evaluator.register_builtin("fail", Arity::Exactly(0), |_| Err(Error::Message("host said no".to_string())));
+
+        let tests = [
+            ("puts(\"a\", 1); puts(sum(1, 2, 3))", "null"),
+            ("sum(1, true)", "ERROR: argument to `sum` not supported, got BOOLEAN"),
+            ("sum()", "ERROR: wrong number of arguments: want=at least 1, got=0"),
+            ("fail()", "ERROR: host said no"),
+            ("fail(1)", "ERROR: wrong number of arguments: want=0, got=1"),
+            ("let apply = fn(f, x) { f(x, x) }; apply(sum, 4)", "8"),
+            ("len([1, 2])", "2"),
+            ("let sum = fn(a) { -a }; sum(1)", "-1"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+        assert_eq!(*printed.borrow(), ["a", "1", "6"]);
+    }
+
+    #[test]
+    fn test_hashes() {
+        let tests = vec![
+            (r#"let two = "two";
+                let h = {"one": 10 - 9, two: 1 + 1, "thr" + "ee": 6 / 2, 4: 4, true: 5, false: 6};
+                [h["one"], h["two"], h["three"], h[4], h[true], h[false]]"#,
+             "[1, 2, 3, 4, 5, 6]"),
+            (r#"{"foo": 5}["foo"]"#, "5"),
+            (r#"{"foo": 5}["bar"]"#, "null"),
+            (r#"let key = "foo"; {"foo": 5}[key]"#, "5"),
+            (r#"{}["foo"]"#, "null"),
+            (r#"{"b": 2, "a": 1}"#, "{a: 1, b: 2}"),
+            (r#"{1: "x", 1: "y"}[1]"#, "y"),
+            (r#"len({"a": 1, "b": 2})"#, "2"),
+            (r#"keys({"b": 2, "a": 1, 3: 3})"#, "[3, a, b]"),
+            (r#"values({"b": 2, "a": 1})"#, "[1, 2]"),
+            (r#"has({"a": 1}, "a")"#, "true"),
+            (r#"has({"a": 1}, "b")"#, "false"),
+            (r#"let h = {"a": 1, "b": 2}; let d = delete(h, "a"); [len(h), len(d), has(d, "a")]"#, "[2, 1, false]"),
+            (r#"{fn(x) { x }: 1}"#, "ERROR: unusable as hash key: FUNCTION"),
+            (r#"{"a": 1}[fn(x) { x }]"#, "ERROR: unusable as hash key: FUNCTION"),
+            (r#"has({"a": 1}, [1])"#, "ERROR: unusable as hash key: ARRAY"),
+            (r#"delete({"a": 1}, {})"#, "ERROR: unusable as hash key: HASH"),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program1.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let tests = vec![
+            ("5 + true;", "type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
+            ("-true", "unknown operator: -BOOLEAN"),
+            ("true + false;", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("if (10 > 1) { true + false; }", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("if (10 > 1) {
+                if (10 > 1) {
+                    return true + false;
+                }
+                return 1;
+              }", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("foobar", "identifier not found: foobar"),
+            (r#""Hello" - "World""#, "unknown operator: STRING - STRING"),
+            ("let f = fn(x) { x + y }; f(1); 2", "identifier not found: y"),
+            ("let x = 1 + true; x", "type mismatch: INTEGER + BOOLEAN"),
+            ("5(1)", "not a function: INTEGER"),
+            ("let f = fn(x, y) { x }; f(1)", "wrong number of arguments: want=2, got=1"),
+            ("[1, 2, foo]", "identifier not found: foo"),
+            ("len(1)", "argument to `len` not supported, got INTEGER"),
+            (r#"len("one", "two")"#, "wrong number of arguments: want=1, got=2"),
+            ("push([], -true)", "unknown operator: -BOOLEAN"),
+            ("1[0]", "index operator not supported: INTEGER[INTEGER]"),
+            ("[1][true]", "index operator not supported: ARRAY[BOOLEAN]"),
+            ("if (missing) { 1 } else { 2 }", "identifier not found: missing"),
+            ("let f = fn() { return nope; }; f() + 1", "identifier not found: nope"),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), Object::Error(test.1.to_string()), "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_mixed_equality() {
+        let tests = vec![
+            ("1 == true", Object::Boolean(false)),
+            ("1 != true", Object::Boolean(true)),
+            (r#""1" == 1"#, Object::Boolean(false)),
+            ("[1, 2] == [1, 2]", Object::Boolean(true)),
+            ("!5", Object::Boolean(false)),
+            ("!!5", Object::Boolean(true)),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_lexical_scope() {
+        let tests = vec![
+            // Closures see bindings made after them in their scope
+            ("let f = fn() { later }; let later = 5; f()", "5"),
+            ("let is_even = fn(n) { if (n == 0) { true } else { is_odd(n - 1) } }; \
+              let is_odd = fn(n) { if (n == 0) { false } else { is_even(n - 1) } }; is_even(10)", "true"),
+            // ...but not the bindings of whoever calls them
+            ("let f = fn() { x }; let g = fn(x) { f() }; g(1)", "ERROR: identifier not found: x"),
+            ("let x = 1; let f = fn() { x }; let g = fn(x) { f() }; g(2)", "1"),
+            ("let adder = fn(a) { fn(b) { a + b } }; let add2 = adder(2); let a = 10; add2(3)", "5"),
+            // Parameters shadow outer names without changing them
+            ("let x = 1; let f = fn(x) { let y = x; y }; f(2) + x", "3"),
+            ("let f = fn() { let inner = 1; inner }; f(); inner", "ERROR: identifier not found: inner"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_assignment() {
+        let tests = vec![
+            ("let x = 1; x = x + 1; x", "2"),
+            ("let x = 1; let y = x = 5; x + y", "10"),
+            ("let a = 1; let b = 2; a = b = 3; a * b", "9"),
+            ("let x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", "6"),
+            ("let s = \"a\"; s += \"b\"; s", "ab"),
+            ("let x = 1.5; x *= 2", "3.0"),
+            // The nearest binding is updated, however far out it is
+            ("let n = 0; let inc = fn() { n += 1 }; inc(); inc(); n", "2"),
+            ("let counter = fn() { let c = 0; fn() { c = c + 1 } }; let next = counter(); next(); next()", "2"),
+            ("let x = 1; let f = fn(x) { x = 5; x }; f(0) + x", "6"),
+            ("let x = 1; let f = fn() { fn() { x = 7 } }; f()(); x", "7"),
+            ("y = 1", "ERROR: assignment to undefined variable: y"),
+            ("y += 1", "ERROR: assignment to undefined variable: y"),
+            ("let f = fn() { let inner = 1; }; f(); inner = 2", "ERROR: assignment to undefined variable: inner"),
+            ("len = 1", "ERROR: assignment to undefined variable: len"),
+            ("let x = 1; x += true; x", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("let x = 1; x = missing; x", "ERROR: identifier not found: missing"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_while_loops() {
+        let tests = vec![
+            ("let i = 0; let sum = 0; while (i < 5) { i += 1; sum += i }; sum", "15"),
+            ("while (false) { 1 }", "null"),
+            ("let i = 0; while (true) { i += 1; if (i == 3) { break } }; i", "3"),
+            // Odd numbers below 10
+            ("let i = 0; let sum = 0; while (i < 10) { i += 1; if (i % 2 == 0) { continue } sum += i }; sum", "25"),
+            // `break` only leaves the innermost loop
+            ("let n = 0; let i = 0; while (i < 3) { i += 1; let j = 0; while (true) { j += 1; if (j > 2) { break } n += 1 } }; n", "6"),
+            ("let f = fn() { let i = 0; while (true) { i += 1; if (i == 4) { return i * 10 } } }; f()", "40"),
+            ("let i = 0; while (i < 3) { i += 1; missing }; i", "ERROR: identifier not found: missing"),
+            // Signals pass straight through the expressions around them
+            ("let i = 0; while (i < 3) { i += 1; let a = if (i == 2) { break }; }; i", "2"),
+            ("let i = 0; while (i < 3) { i += 1; let a = [if (i < 10) { break }]; }; i", "1"),
+            ("let i = 0; while (i < 3) { i += 1; str(if (true) { break }) }; i", "1"),
+            ("let i = 0; while (i < 3) { i += 1; 1 + if (true) { break } }; i", "1"),
+            ("let n = 0; let i = 0; while (i < 3) { i += 1; n += -if (i == 2) { continue } else { 1 } }; n", "-2"),
+            ("let f = fn() { let x = if (true) { return 5 }; 10 }; f()", "5"),
+            ("1 + if (true) { break }", "ERROR: break outside of a loop"),
+            ("while (missing) { }", "ERROR: identifier not found: missing"),
+            ("break", "ERROR: break outside of a loop"),
+            ("if (true) { continue }", "ERROR: continue outside of a loop"),
+            // A function called from a loop can't stop it
+            ("let f = fn() { break }; while (true) { f() }", "ERROR: break outside of a loop"),
+            // Iteration doesn't grow the Rust stack the way recursion does
+            ("let i = 0; while (i < 100000) { i += 1 }; i", "100000"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let tests = vec![
+            ("let sum = 0; for (x in [1, 2, 3]) { sum += x }; sum", "6"),
+            ("let out = \"\"; for (c in \"häj\") { out = c + out }; out", "jäh"),
+            ("let keys = []; for (k in {\"b\": 2, \"a\": 1}) { keys = push(keys, k) }; keys", "[a, b]"),
+            ("let sum = 0; for (k, v in {\"a\": 1, \"b\": 2}) { sum += v }; sum", "3"),
+            ("let out = []; for (i, x in [\"a\", \"b\"]) { out = push(out, i) }; out", "[0, 1]"),
+            ("let sum = 0; for (i in 0..5) { sum += i }; sum", "10"),
+            ("let sum = 0; for (i in 1..=5) { sum += i }; sum", "15"),
+            ("let n = 0; for (i in 5..0) { n += 1 }; n", "0"),
+            ("let n = 0; for (i, x in 10..13) { n += i * x }; n", "35"),
+            ("let n = 0; for (i in 9223372036854775806..=9223372036854775807) { n += 1 }; n", "2"),
+            // Ranges are lazy, so breaking out of a huge one is cheap
+            ("let i = 0; for (x in 0..9223372036854775807) { if (x == 3) { break } i += 1 }; i", "3"),
+            ("let sum = 0; for (x in 0..10) { if (x % 2 == 1) { continue } sum += x }; sum", "20"),
+            ("let find = fn(xs, y) { for (i, x in xs) { if (x == y) { return i } }; -1 }; find([5, 6, 7], 7)", "2"),
+            ("for (x in [1]) { missing }", "ERROR: identifier not found: missing"),
+            // Loop names live in a scope of their own for each iteration
+            ("let x = 100; for (x in [1, 2]) { }; x", "100"),
+            ("let k = 1; let v = 2; for (k, v in {3: 4}) { }; [k, v]", "[1, 2]"),
+            ("for (x in [1]) { let y = x }; y", "ERROR: identifier not found: y"),
+            ("let fs = []; for (i in 0..3) { fs = push(fs, fn() { i }) }; [fs[0](), fs[1](), fs[2]()]", "[0, 1, 2]"),
+            ("let x = 0; for (i in 1..=3) { x = i }; x", "3"),
+            ("for (x in 5) { }", "ERROR: not iterable: INTEGER"),
+            ("1..2.5", "ERROR: range bounds must be integers: INTEGER..FLOAT"),
+            ("let x = 9223372036854775807 * 2; for (i in 0..x) { }", "ERROR: range bound out of range: 18446744073709551614"),
+            ("(-2 ** 64)..=0", "ERROR: range bound out of range: -18446744073709551616"),
+            ("1..=5", "1..=5"),
+        ];
+        for test in tests.iter() {
+            let program = Parser::new(lexer::Lexer::new(test.0)).parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_recursive_function_objects() {
+        let input = "let count = fn(n) { if (n == 0) { 0 } else { 1 + count(n - 1) } }; count";
+        let program = Parser::new(lexer::Lexer::new(input)).parse_program().unwrap();
+        let mut evaluator = evaluator::Evaluator::new();
+        let count = evaluator.eval_statements(&program.statements);
+        // The function's scope holds the function itself; neither may recurse forever
+        assert!(format!("{:?}", count).contains("names: [\"count\"]"));
+        assert_eq!(count, count.clone());
+        let program = Parser::new(lexer::Lexer::new("count(50)")).parse_program().unwrap();
+        assert_eq!(evaluator.eval_statements(&program.statements), Object::Integer(50));
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let tests = vec![
+            ("true && true", Object::Boolean(true)),
+            ("true && false", Object::Boolean(false)),
+            ("false || true", Object::Boolean(true)),
+            ("false || false", Object::Boolean(false)),
+            ("1 && \"\"", Object::Boolean(true)),
+            ("if (false) { 1 } || 0", Object::Boolean(true)),
+            ("1 > 2 || 3 > 2 && 2 > 1", Object::Boolean(true)),
+            // The right side isn't evaluated when the left decides
+            ("false && missing", Object::Boolean(false)),
+            ("true || 1 / 0", Object::Boolean(true)),
+            ("let n = 0; let f = fn() { 1 / n }; n != 0 && f()", Object::Boolean(false)),
+            ("true && missing", Object::Error("identifier not found: missing".to_string())),
+            ("missing || true", Object::Error("identifier not found: missing".to_string())),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            assert_eq!(evaluator.eval_statements(&program1.statements), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let max = "9223372036854775807";
+        let min = "let min = -9223372036854775807 - 1;";
+        let error = |x: &str| Object::Error(x.to_string());
+        let big = |x: &str| Object::BigInt(x.parse().unwrap());
+        // (input, Overflow::Promote, Overflow::Error, Overflow::Wrap)
+        let tests = vec![
+            ("1 / 0".to_string(), error("division by zero"), error("division by zero"), error("division by zero")),
+            (format!("{} + 1", max), big("9223372036854775808"), error("integer overflow: 9223372036854775807 + 1"), Object::Integer(i64::MIN)),
+            (format!("-{} - 2", max), big("-9223372036854775809"), error("integer overflow: -9223372036854775807 - 2"), Object::Integer(i64::MAX)),
+            ("4294967296 * 4294967296".to_string(), big("18446744073709551616"), error("integer overflow: 4294967296 * 4294967296"), Object::Integer(0)),
+            (format!("{} min / -1", min), big("9223372036854775808"), error("integer overflow: -9223372036854775808 / -1"), Object::Integer(i64::MIN)),
+            (format!("{} -min", min), big("9223372036854775808"), error("integer overflow: -(-9223372036854775808)"), Object::Integer(i64::MIN)),
+            (format!("{} - 1 + 1", max), Object::Integer(i64::MAX), Object::Integer(i64::MAX), Object::Integer(i64::MAX)),
+            ("2 ** 64".to_string(), big("18446744073709551616"), error("integer overflow: 2 ** 64"), Object::Integer(0)),
+            ("3 ** 41".to_string(), big("36472996377170786403"), error("integer overflow: 3 ** 41"), Object::Integer(3i64.wrapping_pow(41))),
+            ("3 ** 4294967297".to_string(), error("integer too large: 3 ** 4294967297"), error("integer overflow: 3 ** 4294967297"), Object::Integer(7473929035676909571)),
+            ("1 << 63".to_string(), big("9223372036854775808"), error("integer overflow: 1 << 63"), Object::Integer(i64::MIN)),
+            ("-1 << 70".to_string(), big("-1180591620717411303424"), error("integer overflow: -1 << 70"), Object::Integer(0)),
+            (format!("{} min % -1", min), Object::Integer(0), error("integer overflow: -9223372036854775808 % -1"), Object::Integer(0)),
+        ];
+        for (input, promoted, checked, wrapped) in tests {
+            let program = Parser::new(lexer::Lexer::new(&input)).parse_program().unwrap();
+            let modes = [
+                (evaluator::Overflow::Promote, promoted),
+                (evaluator::Overflow::Error, checked),
+                (evaluator::Overflow::Wrap, wrapped),
+            ];
+            for (mode, expected) in modes {
+                let mut evaluator = evaluator::Evaluator::with_overflow(mode);
+                assert_eq!(evaluator.eval_statements(&program.statements), expected, "{} in {:?}", input, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        let tests = vec![
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % 0", "ERROR: division by zero"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("(-2) ** 3", "-8"),
+            ("2 ** -1", "0.5"),
+            ("0 ** 0", "1"),
+            ("0 ** -1", "ERROR: division by zero"),
+            ("0 ** -(2 ** 70)", "ERROR: division by zero"),
+            ("0.0 ** -0.5", "ERROR: division by zero"),
+            ("0 ** 1", "0"),
+            ("1 <= 1", "true"),
+            ("2 <= 1", "false"),
+            ("1 >= 2", "false"),
+            ("+5 - +-3", "8"),
+            ("~5", "-6"),
+            ("6 & 3", "2"),
+            ("6 | 3", "7"),
+            ("6 ^ 3", "5"),
+            ("1 << 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("1 >> 64", "0"),
+            ("-1 >> 100", "-1"),
+            ("1 << -1", "ERROR: negative shift amount: 1 << -1"),
+            ("1 + 2 * 3 % 4 ** 2", "7"),
+            ("1 | 2 ^ 3 & 4 << 1", "3"),
+            ("7.5 % 2", "1.5"),
+            ("2.0 ** 0.5 > 1.41", "true"),
+            ("2.5 >= 2.5", "true"),
+            ("1.5 & 1", "ERROR: unknown operator: FLOAT & FLOAT"),
+            ("+\"a\"", "ERROR: unknown operator: +STRING"),
+            ("~1.5", "ERROR: unknown operator: ~FLOAT"),
+            ("\"a\" <= \"b\"", "ERROR: unknown operator: STRING <= STRING"),
+            // Big integers
+            ("let b = 2 ** 100; b % 7", "2"),
+            ("let b = 2 ** 100; b ** 2 == 2 ** 200", "true"),
+            ("let b = 2 ** 100; b >> 98", "4"),
+            ("let b = 2 ** 100; -b >> 200", "-1"),
+            ("let b = 2 ** 100; (b | 1) & 3", "1"),
+            ("let b = 2 ** 100; ~b == -b - 1", "true"),
+            ("let b = 2 ** 100; b ^ b", "0"),
+            ("let b = 2 ** 100; b <= b + 1", "true"),
+            ("let b = 2 ** 100; (b << 1) / b", "2"),
+            ("let b = 2 ** 100; 1 ** b", "1"),
+            ("let b = 2 ** 100; (-1) ** (b + 1)", "-1"),
+            ("let b = 2 ** 100; 2 ** -b", "0.0"),
+            ("let b = 2 ** 100; 2 << b", "ERROR: integer too large: 2 << 1267650600228229401496703205376"),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program1.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let tests = vec![
+            ("let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(25)", "15511210043330985984000000"),
+            ("let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(30) / fact(28)", "870"),
+            ("9223372036854775807 + 1 - 1", "9223372036854775807"),
+            ("(9223372036854775807 + 1) > 9223372036854775807", "true"),
+            ("(9223372036854775807 + 1) == 9223372036854775807 + 1", "true"),
+            ("-(9223372036854775807 + 10)", "-9223372036854775817"),
+            ("(9223372036854775807 * 2) / 0", "ERROR: division by zero"),
+            ("(9223372036854775807 * 2) + true", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("let h = {9223372036854775807 * 2: 1}; h[9223372036854775807 + 9223372036854775807]", "1"),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program1.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
+
+    #[test]
+    fn test_floats() {
+        let tests = vec![
+            ("3.14", "3.14"),
+            ("1e-9", "1e-9"),
+            ("2.0", "2.0"),
+            ("-2.5", "-2.5"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1.5 * 2", "3.0"),
+            ("2 * 1.5", "3.0"),
+            ("7 / 2", "3"),
+            ("7 / 2.0", "3.5"),
+            ("10 - 0.25", "9.75"),
+            ("let price = 19.99; let qty = 3; price * qty", "59.97"),
+            ("(9223372036854775807 + 1) * 0.5", "4.611686018427388e18"),
+            ("1 < 1.5", "true"),
+            ("2.5 > 3", "false"),
+            ("1 == 1.0", "true"),
+            ("1.5 != 1.5", "false"),
+            ("1.0 / 0", "ERROR: division by zero"),
+            ("1.5 + true", "ERROR: type mismatch: FLOAT + BOOLEAN"),
+            (r#""a" + 1.5"#, "ERROR: type mismatch: STRING + FLOAT"),
+            ("{1.5: 1}", "ERROR: unusable as hash key: FLOAT"),
+        ];
+        for test in tests.iter() {
+            let l1 = lexer::Lexer::new(test.0);
+            let mut p1 = Parser::new(l1);
+            let program1 = p1.parse_program().unwrap();
+            let mut evaluator = evaluator::Evaluator::new();
+            let result = evaluator.eval_statements(&program1.statements);
+            assert_eq!(evaluator::Evaluator::unwrap_object(result), test.1, "{}", test.0)
+        }
+    }
 }
@@ -0,0 +1,432 @@
+//! The source formatter behind `monkey fmt`.
+//!
+//! Code is printed from the `ast` in one fixed style: four space indentation,
+//! one statement per line ending in `;` (except loops and the last
+//! expression of a block), spaces around infix operators, only the
+//! parentheses precedence requires, and call arguments one per line when the
+//! call gets too long.
+//! Comments and blank lines between statements are taken from the concrete
+//! syntax tree; comments inside a statement move up in front of it.
+
+use crate::{
+    ast::{BlockStatement, Expression, Statement, Sticky},
+    cst::{self, SyntaxElement, SyntaxKind, SyntaxNode},
+    parser::{ParseError, Parser},
+    token::Token,
+};
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+
+/// Formats Monkey source, or returns the errors if it doesn't parse.
+pub fn format(source: &str) -> Result<String, Vec<ParseError>> {
+    let (tree, program) = cst::parse(source);
+    let program = program?;
+    let mut blocks = vec![];
+    collect_block(&tree, &mut blocks);
+    let mut printer = Printer { blocks, next_block: 0 };
+    Ok(printer.statements(&program.statements, 0, true))
+}
+
+#[derive(Debug, Default)]
+struct StatementTrivia {
+    blank_before: bool,
+    leading: Vec<String>,
+    trailing: Option<String>,
+}
+
+// Comments of one block, or of the whole program, by statement.
+#[derive(Debug, Default)]
+struct BlockTrivia {
+    statements: Vec<StatementTrivia>,
+    end: Vec<String>,
+    // A blank line between the last statement and the `end` comments
+    blank_before_end: bool,
+}
+
+// Blocks are numbered in source order, the same order the printer meets
+// their `BlockStatement`s in.
+fn collect_block(node: &SyntaxNode, blocks: &mut Vec<BlockTrivia>) {
+    let index = blocks.len();
+    blocks.push(BlockTrivia::default());
+    let mut block = BlockTrivia::default();
+    let mut pending = StatementTrivia::default();
+    // Comments on the same line as a statement trail it
+    let mut same_line = false;
+    // Whether a blank line came before the first of `pending.leading`
+    let mut blank_before_comments = false;
+    for child in &node.children {
+        match child {
+            SyntaxElement::Token(token) => match &token.token {
+                Token::Whitespace(space) => {
+                    let newlines = space.matches('\n').count();
+                    same_line &= newlines == 0;
+                    pending.blank_before |= newlines > 1;
+                }
+                Token::Comment(comment) => {
+                    let comment = comment.trim_end().to_string();
+                    match block.statements.last_mut() {
+                        Some(last) if same_line && last.trailing.is_none() => last.trailing = Some(comment),
+                        _ => {
+                            if pending.leading.is_empty() {
+                                blank_before_comments = pending.blank_before;
+                            }
+                            pending.leading.push(comment)
+                        }
+                    }
+                }
+                _ => {}
+            },
+            SyntaxElement::Node(statement) => {
+                collect_nested(statement, &mut pending.leading, blocks);
+                block.statements.push(std::mem::take(&mut pending));
+                same_line = true;
+            }
+        }
+    }
+    block.blank_before_end = blank_before_comments && !pending.leading.is_empty();
+    block.end = pending.leading;
+    blocks[index] = block;
+}
+
+fn collect_nested(node: &SyntaxNode, comments: &mut Vec<String>, blocks: &mut Vec<BlockTrivia>) {
+    for child in &node.children {
+        match child {
+            SyntaxElement::Token(token) => {
+                if let Token::Comment(comment) = &token.token {
+                    comments.push(comment.trim_end().to_string());
+                }
+            }
+            SyntaxElement::Node(node) if node.kind == SyntaxKind::Block => collect_block(node, blocks),
+            SyntaxElement::Node(node) => collect_nested(node, comments, blocks),
+        }
+    }
+}
+
+struct Printer {
+    blocks: Vec<BlockTrivia>,
+    next_block: usize,
+}
+
+impl Printer {
+    // One line per statement, each indented by `depth` and ending in `\n`.
+    fn statements(&mut self, statements: &[Statement], depth: usize, top_level: bool) -> String {
+        let index = self.next_block;
+        self.next_block += 1;
+        let trivia = match self.blocks.get_mut(index) {
+            Some(trivia) => std::mem::take(trivia),
+            None => BlockTrivia::default(),
+        };
+        let pad = INDENT.repeat(depth);
+        let mut out = String::new();
+        for (i, statement) in statements.iter().enumerate() {
+            let statement_trivia = trivia.statements.get(i);
+            if let Some(statement_trivia) = statement_trivia {
+                if statement_trivia.blank_before && i > 0 {
+                    out.push('\n');
+                }
+                for comment in &statement_trivia.leading {
+                    out += &format!("{}{}\n", pad, comment);
+                }
+            }
+            let tail = !top_level && i == statements.len() - 1;
+            out += &pad;
+            out += &self.statement(statement, depth, tail);
+            if let Some(comment) = statement_trivia.and_then(|t| t.trailing.as_ref()) {
+                out += &format!(" {}", comment);
+            }
+            out.push('\n');
+        }
+        if trivia.blank_before_end && !statements.is_empty() && !trivia.end.is_empty() {
+            out.push('\n');
+        }
+        for comment in &trivia.end {
+            out += &format!("{}{}\n", pad, comment);
+        }
+        // Printing the same block twice, see `call`, has to find its comments again
+        if let Some(slot) = self.blocks.get_mut(index) {
+            *slot = trivia;
+        }
+        out
+    }
+
+    fn statement(&mut self, statement: &Statement, depth: usize, tail: bool) -> String {
+        let column = INDENT.len() * depth;
+        match statement {
+            Statement::LetStatement(name, value) => {
+                let prefix = format!("let {} = ", name);
+                let value = self.expression(value, depth, column + width(&prefix));
+                format!("{}{};", prefix, value)
+            }
+            Statement::ReturnStatement(value) => format!("return {};", self.expression(value, depth, column + 7)),
+            Statement::ExpressionStatement(expr) if tail => self.expression(expr, depth, column),
+            Statement::ExpressionStatement(expr) => format!("{};", self.expression(expr, depth, column)),
+            Statement::WhileStatement(condition, body) => {
+                let condition = self.expression(condition, depth, column + 7);
+                format!("while ({}) {}", condition, self.block(Some(body), depth))
+            }
+            Statement::ForStatement(name, value, iterable, body) => {
+                let names = match value {
+                    Some(value) => format!("{}, {}", name, value),
+                    None => name.to_string(),
+                };
+                let prefix = format!("for ({} in ", names);
+                let iterable = self.expression(iterable, depth, column + width(&prefix));
+                format!("{}{}) {}", prefix, iterable, self.block(Some(body), depth))
+            }
+            Statement::BreakStatement => "break;".to_string(),
+            Statement::ContinueStatement => "continue;".to_string(),
+        }
+    }
+
+    fn block(&mut self, block: Option<&BlockStatement>, depth: usize) -> String {
+        let body = match block {
+            Some(block) => self.statements(&block.statements, depth + 1, false),
+            None => String::new(),
+        };
+        if body.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{\n{}{}}}", body, INDENT.repeat(depth))
+        }
+    }
+
+    // `column` is where on its line the expression starts.
+    fn expression(&mut self, expr: &Expression, depth: usize, column: usize) -> String {
+        match expr {
+            Expression::Prefix(op, right) => {
+                let op = op.to_string();
+                let right = self.operand(right, Sticky::PREFIX, depth, column + width(&op));
+                op + &right
+            }
+            Expression::Infix(op, left, right) => {
+                let stickiness = Parser::exp_to_sticky(op);
+                // `**` is right associative, all other operators left
+                // associative: an equal operand on the other side needs parentheses
+                let right_associative = *op == Token::Power;
+                let left = if right_associative && precedence(left) <= stickiness {
+                    format!("({})", self.expression(left, depth, column + 1))
+                } else {
+                    self.operand(left, Parser::exp_to_sticky(op), depth, column)
+                };
+                let op = format!(" {} ", op);
+                let column = end_column(column, &left) + width(&op);
+                let right = if precedence(right) < stickiness || !right_associative && precedence(right) == stickiness {
+                    format!("({})", self.expression(right, depth, column + 1))
+                } else {
+                    self.expression(right, depth, column)
+                };
+                left + &op + &right
+            }
+            Expression::If(condition, consequence, alternative) => {
+                let condition = self.expression(condition, depth, column + 4);
+                let mut out = format!("if ({}) {}", condition, self.block(consequence.as_ref(), depth));
+                if alternative.is_some() {
+                    out += &format!(" else {}", self.block(alternative.as_ref(), depth));
+                }
+                out
+            }
+            Expression::Function(params, body) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                format!("fn({}) {}", params.join(", "), self.block(body.as_ref(), depth))
+            }
+            Expression::Call(function, args) => self.call(function, args, depth, column),
+            Expression::Array(elements) => format!("[{}]", self.list(elements, depth, column + 1)),
+            Expression::Hash(pairs) => {
+                let mut out = "{".to_string();
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        out += ", ";
+                    }
+                    let key = self.expression(key, depth, end_column(column, &out));
+                    out += &key;
+                    out += ": ";
+                    let value = self.expression(value, depth, end_column(column, &out));
+                    out += &value;
+                }
+                out + "}"
+            }
+            Expression::Index(left, index) => {
+                let left = self.operand(left, Sticky::CALL, depth, column);
+                let index = self.expression(index, depth, end_column(column, &left) + 1);
+                format!("{}[{}]", left, index)
+            }
+            // `..` binds looser than every infix operator and groups to the left
+            Expression::Range(start, end, inclusive) => {
+                let start = self.operand(start, Sticky::RANGE, depth, column);
+                let op = if *inclusive { "..=" } else { ".." };
+                let column = end_column(column, &start) + op.len();
+                let end = if precedence(end) <= Sticky::RANGE {
+                    format!("({})", self.expression(end, depth, column + 1))
+                } else {
+                    self.expression(end, depth, column)
+                };
+                start + op + &end
+            }
+            Expression::Assign(op, name, value) => {
+                let target = format!("{} {} ", name, op);
+                let value = self.expression(value, depth, column + width(&target));
+                target + &value
+            }
+            _ => expr.to_string(),
+        }
+    }
+
+    // `expr` in parentheses if it binds less tightly than `min`.
+    fn operand(&mut self, expr: &Expression, min: Sticky, depth: usize, column: usize) -> String {
+        if precedence(expr) < min {
+            format!("({})", self.expression(expr, depth, column + 1))
+        } else {
+            self.expression(expr, depth, column)
+        }
+    }
+
+    fn list(&mut self, exprs: &[Expression], depth: usize, column: usize) -> String {
+        let mut out = String::new();
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                out += ", ";
+            }
+            let expr = self.expression(expr, depth, end_column(column, &out));
+            out += &expr;
+        }
+        out
+    }
+
+    // Arguments go one per line when the first line of the call would end
+    // past `MAX_WIDTH`.
+    fn call(&mut self, function: &Expression, args: &[Expression], depth: usize, column: usize) -> String {
+        let function = self.operand(function, Sticky::CALL, depth, column);
+        let first_block = self.next_block;
+        let flat = format!("{}({})", function, self.list(args, depth, end_column(column, &function) + 1));
+        let first_line = flat.lines().next().unwrap_or("");
+        if args.is_empty() || column + width(first_line) <= MAX_WIDTH {
+            return flat;
+        }
+        self.next_block = first_block;
+        let pad = INDENT.repeat(depth + 1);
+        let args: Vec<String> = args.iter().map(|arg| pad.clone() + &self.expression(arg, depth + 1, pad.len())).collect();
+        format!("{}(\n{}\n{})", function, args.join(",\n"), INDENT.repeat(depth))
+    }
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+// The column after `s` when it is printed starting at `column`.
+fn end_column(column: usize, s: &str) -> usize {
+    match s.rfind('\n') {
+        Some(i) => width(&s[i + 1..]),
+        None => column + width(s),
+    }
+}
+
+fn precedence(expr: &Expression) -> Sticky {
+    match expr {
+        Expression::Prefix(..) => Sticky::PREFIX,
+        Expression::Infix(op, ..) => Parser::exp_to_sticky(op),
+        Expression::Call(..) => Sticky::CALL,
+        Expression::Assign(..) => Sticky::ASSIGN,
+        Expression::Range(..) => Sticky::RANGE,
+        _ => Sticky::INDEX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn assert_formats(input: &str, expected: &str) {
+        let formatted = format(input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted, "formatting isn't stable");
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let reformatted = Parser::new(Lexer::new(&formatted)).parse_program().unwrap();
+        assert_eq!(reformatted, program);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_formats("", "");
+        assert_formats(
+            "let add=fn(a,b){a+b};let x=add( 1 ,2)*-3;puts(x)",
+            "let add = fn(a, b) {\n    a + b\n};\nlet x = add(1, 2) * -3;\nputs(x);\n",
+        );
+        assert_formats(
+            "if(x<y){return x;}else{y;} let h={\"a\":[1,2][0],true:!false};fn(){}",
+            "if (x < y) {\n    return x;\n} else {\n    y\n};\nlet h = {\"a\": [1, 2][0], true: !false};\nfn() {};\n",
+        );
+        assert_formats(
+            "let f = fn(x) { let g = fn(y) { if (y) { x } }; g(x) };",
+            "let f = fn(x) {\n    let g = fn(y) {\n        if (y) {\n            x\n        }\n    };\n    g(x)\n};\n",
+        );
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_formats(
+            "((a + b) + c) * (d - (e - f)) / -(g + 1) == (1 < 2);",
+            "(a + b + c) * (d - (e - f)) / -(g + 1) == 1 < 2;\n",
+        );
+        assert_formats("(-a)[0]; (f)(1)(2); -f(x); (a * b)(c); !(!x)", "(-a)[0];\nf(1)(2);\n-f(x);\n(a * b)(c);\n!!x;\n");
+        assert_formats(
+            "(2 ** 3) ** (4 ** 5); (-2) ** 2; -(2 ** 2); a % (b % c); (a | b) & c",
+            "(2 ** 3) ** 4 ** 5;\n(-2) ** 2;\n-2 ** 2;\na % (b % c);\n(a | b) & c;\n",
+        );
+        assert_formats(
+            "x = (y = (1 + 2)); (x = 1) + 2; x += (a || b); f(x *= 2)",
+            "x = y = 1 + 2;\n(x = 1) + 2;\nx += a || b;\nf(x *= 2);\n",
+        );
+    }
+
+    #[test]
+    fn test_long_calls() {
+        let input = "let result = some_function(first_argument_value, second_argument_value, third_argument_value, fn(x) { x * 2 });";
+        assert_formats(
+            input,
+            "let result = some_function(\n    first_argument_value,\n    second_argument_value,\n    third_argument_value,\n    fn(x) {\n        x * 2\n    }\n);\n",
+        );
+        // Only the first line counts
+        assert_formats(
+            "map(elements, fn(element) { let doubled = element * 2; doubled + some_long_name_to_make_this_longer })",
+            "map(elements, fn(element) {\n    let doubled = element * 2;\n    doubled + some_long_name_to_make_this_longer\n});\n",
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_formats(
+            "while(i<10){i+=1;if(i==5){continue};if(done){break;}} ; x",
+            "while (i < 10) {\n    i += 1;\n    if (i == 5) {\n        continue;\n    };\n    if (done) {\n        break;\n    }\n}\nx;\n",
+        );
+        assert_formats("while (true) { } // forever", "while (true) {} // forever\n");
+        assert_formats(
+            "for(k,v in h){puts(k)} for (i in (0..(n+1))) { } for (x in (a..b)..(c..=d)) { }",
+            "for (k, v in h) {\n    puts(k)\n}\nfor (i in 0..n + 1) {}\nfor (x in a..b..(c..=d)) {}\n",
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_formats(
+            "// header\nlet x = 1;   // one\n\n\n/* about y */ let y = fn() {\n  // inside\n  x // tail\n  /* end */ };\nputs(x, /* arg */ y) ;\n// last",
+            "// header\nlet x = 1; // one\n\n/* about y */\nlet y = fn() {\n    // inside\n    x // tail\n    /* end */\n};\n/* arg */\nputs(x, y);\n// last\n",
+        );
+        // Blank lines before comments at the end of a block are kept, one at most
+        assert_formats("let a = 1;\n\n// end\n", "let a = 1;\n\n// end\n");
+        assert_formats("let a = 1;\n\n\n// end\n// more\n\n", "let a = 1;\n\n// end\n// more\n");
+        assert_formats("let a = 1;\n// end\n\n", "let a = 1;\n// end\n");
+        assert_formats(
+            "let f = fn() {\n    1\n\n    // done\n};\n",
+            "let f = fn() {\n    1\n\n    // done\n};\n",
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let errors = format("let = 5;").unwrap_err();
+        assert_eq!(errors[0].to_string(), "expected identifier, found `=`");
+    }
+}
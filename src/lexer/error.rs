@@ -0,0 +1,46 @@
+use crate::token::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LexError {
+    /// A character that cannot start any token.
+    UnexpectedChar { found: char, span: Span },
+    /// A string literal without its closing `"`. The span covers the opening quote.
+    UnterminatedString { span: Span },
+    /// A `/*` comment without its closing `*/`. The span covers the opening `/*`.
+    UnterminatedComment { span: Span },
+    /// An unknown or malformed `\` escape inside a string literal.
+    InvalidEscape { escape: String, span: Span },
+    /// An integer literal too large for a 64-bit integer.
+    IntegerOverflow { literal: String, span: Span },
+    /// A float literal too large for a 64-bit float.
+    FloatOverflow { literal: String, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedChar { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedComment { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::IntegerOverflow { span, .. }
+            | LexError::FloatOverflow { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnexpectedChar { found, .. } => write!(f, "unexpected character {:?}", found),
+            LexError::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            LexError::UnterminatedComment { .. } => write!(f, "unterminated block comment"),
+            LexError::InvalidEscape { escape, .. } => write!(f, "invalid escape sequence `{}`", escape),
+            LexError::IntegerOverflow { literal, .. } => write!(f, "integer literal {} is too large", literal),
+            LexError::FloatOverflow { literal, .. } => write!(f, "float literal {} is too large", literal),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
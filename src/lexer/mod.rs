@@ -1,21 +1,53 @@
-use crate::token::Token;
+mod error;
+
+pub use error::LexError;
+
+use crate::token::{Span, SpannedToken, Token};
+use std::collections::VecDeque;
+use unicode_xid::UnicodeXID;
+
+/// Lexes all of `input`, up to but not including `Token::EOF`. Erroneous
+/// input shows up as `Token::Illegal`; use a `Lexer` to get the errors.
+pub fn tokenize(input: &str) -> Vec<SpannedToken> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = vec![];
+    loop {
+        let spanned = lexer.next_spanned_token();
+        if spanned.token == Token::EOF {
+            return tokens;
+        }
+        tokens.push(spanned);
+    }
+}
+
+/// Positions and spans are byte offsets into the input, columns count
+/// `char`s.
 #[derive(Debug)]
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
     read_position: usize,
-    current_char: u8,
+    // '\0' once the input is exhausted
+    current_char: char,
+    // 1-based line and column of `current_char`
+    line: usize,
+    column: usize,
+    errors: Vec<LexError>,
+    // Tokens lexed ahead by `peek_n`
+    peeked: VecDeque<SpannedToken>,
+    // Whether whitespace and comments are returned as tokens
+    trivia: bool,
 }
 
 impl<'a> Lexer<'a> {
+    // Identifiers are Unicode XID identifiers, plus `_` anywhere
     fn is_letter(&self) -> bool {
-        let letter = self.current_char;
-        (letter >= b'a' && letter <= b'z') || (letter >= b'A' && letter <= b'Z') || letter == b'_'
+        self.current_char == '_' || self.current_char.is_xid_start()
     }
 
     fn read_identifier(&mut self) -> Token {
         let initial_position = self.position;
-        while self.is_letter() {
+        while self.current_char == '_' || self.current_char.is_xid_continue() {
             self.read_char();
         }
         let identifier = &self.input[initial_position..self.position];
@@ -28,43 +60,282 @@ impl<'a> Lexer<'a> {
             "if" => Token::If,
             "else" => Token::Else,
             "return" => Token::Return,
+            "while" => Token::While,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "for" => Token::For,
+            "in" => Token::In,
             _ => Token::Ident(identifier.to_string()),
         }
     }
 
     fn read_digit(&mut self) -> Token {
-        let initial_position = self.position;
+        let (initial_position, line, column) = (self.position, self.line, self.column);
         while self.is_digit() {
             self.read_char();
         }
+        let mut is_float = false;
+        // Only a `.` followed by a digit starts a fraction
+        if self.current_char == '.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.read_char();
+            while self.is_digit() {
+                self.read_char();
+            }
+        }
+        if (self.current_char == 'e' || self.current_char == 'E') && self.exponent_follows() {
+            is_float = true;
+            self.read_char();
+            if self.current_char == '+' || self.current_char == '-' {
+                self.read_char();
+            }
+            while self.is_digit() {
+                self.read_char();
+            }
+        }
         let num = &self.input[initial_position..self.position];
-        Token::Int(
-            num.parse::<i32>()
-                .expect("Invalid identifier or number!"),
-        )
+        if is_float {
+            return match num.parse::<f64>() {
+                Ok(x) if x.is_finite() => Token::Float(x),
+                _ => {
+                    self.errors.push(LexError::FloatOverflow {
+                        literal: num.to_string(),
+                        span: Span { start: initial_position, end: self.position, line, column },
+                    });
+                    Token::Illegal
+                }
+            };
+        }
+        match num.parse::<i64>() {
+            Ok(x) => Token::Int(x),
+            Err(_) => {
+                self.errors.push(LexError::IntegerOverflow {
+                    literal: num.to_string(),
+                    span: Span { start: initial_position, end: self.position, line, column },
+                });
+                Token::Illegal
+            }
+        }
+    }
+
+    // Called with `current_char` on the opening quote.
+    fn read_string(&mut self) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.read_char();
+        let mut value = String::new();
+        let mut segment_start = self.position;
+        loop {
+            match self.current_char {
+                '"' => break,
+                '\0' if self.at_end() => {
+                    let span = Span { start, end: start + 1, line, column };
+                    self.errors.push(LexError::UnterminatedString { span });
+                    return Token::Illegal;
+                }
+                '\\' => {
+                    value.push_str(&self.input[segment_start..self.position]);
+                    self.read_escape(&mut value);
+                    segment_start = self.position;
+                }
+                _ => self.read_char(),
+            }
+        }
+        value.push_str(&self.input[segment_start..self.position]);
+        self.read_char();
+        Token::Str(value)
+    }
+
+    // Called with `current_char` on the backslash. Invalid escapes are
+    // reported and kept in the string as written.
+    fn read_escape(&mut self, value: &mut String) {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.read_char();
+        let escaped = match self.current_char {
+            'n' => '\n',
+            't' => '\t',
+            '"' => '"',
+            '\\' => '\\',
+            'u' => return self.read_unicode_escape(value, start, line, column),
+            _ => {
+                // Don't swallow the end of input, `read_string` reports it
+                if !self.at_end() {
+                    self.read_char();
+                }
+                let escape = &self.input[start..self.position];
+                value.push_str(escape);
+                self.errors.push(LexError::InvalidEscape {
+                    escape: escape.to_string(),
+                    span: Span { start, end: self.position, line, column },
+                });
+                return;
+            }
+        };
+        self.read_char();
+        value.push(escaped);
+    }
+
+    // `\u{XXXX}` with 1 to 6 hex digits naming a Unicode scalar value.
+    fn read_unicode_escape(&mut self, value: &mut String, start: usize, line: usize, column: usize) {
+        self.read_char();
+        let mut c = None;
+        if self.current_char == '{' {
+            self.read_char();
+            let digits_start = self.position;
+            while self.current_char.is_ascii_hexdigit() {
+                self.read_char();
+            }
+            let digits = &self.input[digits_start..self.position];
+            if self.current_char == '}' && !digits.is_empty() && digits.len() <= 6 {
+                self.read_char();
+                c = u32::from_str_radix(digits, 16).ok().and_then(char::from_u32);
+            }
+        }
+        match c {
+            Some(c) => value.push(c),
+            None => {
+                let escape = &self.input[start..self.position];
+                value.push_str(escape);
+                self.errors.push(LexError::InvalidEscape {
+                    escape: escape.to_string(),
+                    span: Span { start, end: self.position, line, column },
+                });
+            }
+        }
     }
 
     fn skip_whitespaces(&mut self) {
-        while self.current_char == b' '
-            || self.current_char == b'\t'
-            || self.current_char == b'\n'
-            || self.current_char == b'\r'
+        while self.current_char == ' '
+            || self.current_char == '\t'
+            || self.current_char == '\n'
+            || self.current_char == '\r'
         {
             self.read_char();
         }
     }
 
+    // Skips a `// ...` or (nested) `/* ... */` comment if there is one at
+    // `current_char`, returning whether it did.
+    fn skip_comment(&mut self) -> bool {
+        if self.current_char != '/' {
+            return false;
+        }
+        match self.peek_char() {
+            '/' => {
+                while self.current_char != '\n' && !self.at_end() {
+                    self.read_char();
+                }
+                true
+            }
+            '*' => {
+                let (start, line, column) = (self.position, self.line, self.column);
+                let mut depth = 0;
+                loop {
+                    if self.at_end() {
+                        let span = Span { start, end: start + 2, line, column };
+                        self.errors.push(LexError::UnterminatedComment { span });
+                        break;
+                    }
+                    match (self.current_char, self.peek_char()) {
+                        ('/', '*') => {
+                            depth += 1;
+                            self.read_char();
+                        }
+                        ('*', '/') => {
+                            depth -= 1;
+                            self.read_char();
+                            if depth == 0 {
+                                self.read_char();
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.read_char();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn is_digit(&self) -> bool {
-        let letter = self.current_char;
-        letter >= b'0' && letter <= b'9'
+        self.current_char.is_ascii_digit()
     }
     pub fn next_token(&mut self) -> Token {
+        self.next_spanned_token().token
+    }
+
+    /// Takes the errors found so far. Every erroneous piece of input is
+    /// reported here once; the token produced for it is usually `Token::Illegal`.
+    pub fn take_errors(&mut self) -> Vec<LexError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Like `next_token`, but also returns where in the input the token was found.
+    pub fn next_spanned_token(&mut self) -> SpannedToken {
+        match self.peeked.pop_front() {
+            Some(spanned) => spanned,
+            None => self.lex_token(),
+        }
+    }
+
+    /// Looks at the token `n` places ahead without consuming anything;
+    /// `peek_n(0)` is the token `next_spanned_token` returns next. Errors
+    /// in the tokens peeked at are reported by `take_errors` right away.
+    pub fn peek_n(&mut self, n: usize) -> &SpannedToken {
+        while self.peeked.len() <= n {
+            let spanned = self.lex_token();
+            self.peeked.push_back(spanned);
+        }
+        &self.peeked[n]
+    }
+
+    fn lex_token(&mut self) -> SpannedToken {
+        if !self.trivia {
+            self.skip_whitespaces();
+            while self.skip_comment() {
+                self.skip_whitespaces();
+            }
+        }
+        let start = self.position;
+        let line = self.line;
+        let column = self.column;
+        let token = match self.read_trivia() {
+            Some(trivia) => trivia,
+            None => self.read_token(),
+        };
+        SpannedToken {
+            token,
+            span: Span {
+                start,
+                end: self.position,
+                line,
+                column,
+            },
+        }
+    }
+
+    fn read_trivia(&mut self) -> Option<Token> {
+        let start = self.position;
+        if !self.trivia {
+            return None;
+        }
         self.skip_whitespaces();
+        if self.position > start {
+            return Some(Token::Whitespace(self.input[start..self.position].to_string()));
+        }
+        if self.skip_comment() {
+            return Some(Token::Comment(self.input[start..self.position].to_string()));
+        }
+        None
+    }
+
+    fn read_token(&mut self) -> Token {
         match self.current_char {
             // Symbols
-            0 => Token::EOF,
-            b'=' => {
-                if self.peek_char() == b'=' {
+            '\0' if self.at_end() => Token::EOF,
+            '=' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     self.read_char();
                     Token::Eq
@@ -73,40 +344,46 @@ impl<'a> Lexer<'a> {
                     Token::Assign
                 }
             }
-            b';' => {
+            ';' => {
                 self.read_char();
                 Token::Semicolon
             }
-            b'(' => {
+            ':' => {
+                self.read_char();
+                Token::Colon
+            }
+            '(' => {
                 self.read_char();
                 Token::LParen
             }
-            b')' => {
+            ')' => {
                 self.read_char();
                 Token::RParen
             }
-            b',' => {
+            ',' => {
                 self.read_char();
                 Token::Comma
             }
-            b'+' => {
-                self.read_char();
-                Token::Plus
-            }
-            b'{' => {
+            '+' => self.read_operator(Token::Plus, &[('=', Token::PlusAssign)]),
+            '{' => {
                 self.read_char();
                 Token::LBrace
             }
-            b'}' => {
+            '}' => {
                 self.read_char();
                 Token::RBrace
             }
-            b'-' => {
+            '[' => {
                 self.read_char();
-                Token::Minus
+                Token::LBracket
             }
-            b'!' => {
-                if self.peek_char() == b'=' {
+            ']' => {
+                self.read_char();
+                Token::RBracket
+            }
+            '-' => self.read_operator(Token::Minus, &[('=', Token::MinusAssign)]),
+            '!' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     self.read_char();
                     Token::NotEq
@@ -115,22 +392,30 @@ impl<'a> Lexer<'a> {
                     Token::Bang
                 }
             }
-            b'*' => {
+            '*' => self.read_operator(Token::Asterisk, &[('*', Token::Power), ('=', Token::AsteriskAssign)]),
+            '%' => {
                 self.read_char();
-                Token::Asterisk
+                Token::Percent
             }
-            b'/' => {
+            '&' => self.read_operator(Token::Ampersand, &[('&', Token::And)]),
+            '|' => self.read_operator(Token::Pipe, &[('|', Token::Or)]),
+            '^' => {
                 self.read_char();
-                Token::Slash
+                Token::Caret
             }
-            b'<' => {
+            '~' => {
                 self.read_char();
-                Token::Lt
+                Token::Tilde
             }
-            b'>' => {
+            '/' => self.read_operator(Token::Slash, &[('=', Token::SlashAssign)]),
+            '<' => self.read_operator(Token::Lt, &[('=', Token::LtEq), ('<', Token::Shl)]),
+            '>' => self.read_operator(Token::Gt, &[('=', Token::GtEq), ('>', Token::Shr)]),
+            // A lone `.` is illegal
+            '.' if self.peek_char() == '.' => {
                 self.read_char();
-                Token::Gt
+                self.read_operator(Token::DotDot, &[('=', Token::DotDotEq)])
             }
+            '"' => self.read_string(),
             _ => {
                 //isLetter
                 if self.is_letter() {
@@ -138,39 +423,98 @@ impl<'a> Lexer<'a> {
                 } else if self.is_digit() {
                     self.read_digit()
                 } else {
+                    let (start, line, column) = (self.position, self.line, self.column);
+                    let found = self.current_char;
+                    self.read_char();
+                    let span = Span { start, end: self.position, line, column };
+                    self.errors.push(LexError::UnexpectedChar { found, span });
                     Token::Illegal
                 }
             }
         }
     }
 
-    fn peek_char(&self) -> u8 {
-        let l = self.input.len();
-        if self.read_position >= l {
-            0
-        } else {
-            self.input.as_bytes()[self.read_position]
+    // A one character operator, or a two character one if the next character
+    // is in `longer`.
+    fn read_operator(&mut self, single: Token, longer: &[(char, Token)]) -> Token {
+        let next = self.peek_char();
+        self.read_char();
+        match longer.iter().find(|(c, _)| *c == next) {
+            Some((_, token)) => {
+                self.read_char();
+                token.clone()
+            }
+            None => single,
+        }
+    }
+
+    // With `current_char` on an `e`, whether an exponent (`e5`, `e-9`) follows.
+    fn exponent_follows(&self) -> bool {
+        let mut rest = self.input[self.read_position..].chars();
+        match rest.next() {
+            Some('+') | Some('-') => rest.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
         }
     }
 
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn peek_char(&self) -> char {
+        self.input[self.read_position..].chars().next().unwrap_or('\0')
+    }
+
     fn read_char(&mut self) {
-        let l = self.input.len();
-        if self.read_position >= l {
-            self.current_char = 0;
+        if self.current_char == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.current_char = self.input.as_bytes()[self.read_position];
+            self.column += 1;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.read_position..].chars().next() {
+            Some(c) => {
+                self.current_char = c;
+                self.read_position += c.len_utf8();
+            }
+            None => self.current_char = '\0',
+        }
     }
     pub fn new(input: &'a str) -> Self {
         let mut l = Lexer {
             input,
             position: 0,
             read_position: 0,
-            current_char: 0,
+            current_char: '\0',
+            line: 1,
+            column: 0,
+            errors: vec![],
+            peeked: VecDeque::new(),
+            trivia: false,
         };
         l.read_char();
         l
     }
+
+    /// A lexer that also returns whitespace and comments, as `Token::Whitespace`
+    /// and `Token::Comment`. Together the tokens cover every byte of `input`.
+    pub fn with_trivia(input: &'a str) -> Self {
+        let mut l = Lexer::new(input);
+        l.trivia = true;
+        l
+    }
+}
+
+/// Yields the tokens up to, but not including, `Token::EOF`.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        match self.next_token() {
+            Token::EOF => None,
+            token => Some(token),
+        }
+    }
 }
@@ -6,19 +6,45 @@ use crate::evaluator::Evaluator;
 pub mod lexer;
 pub mod token;
 pub mod ast;
+pub mod cst;
 pub mod parser;
-pub(crate) mod object;
+pub mod object;
 pub mod evaluator;
+pub mod formatter;
 
 #[wasm_bindgen]
 pub fn eval_monkey_program(input: String) -> String {
     let mut evaluator = evaluator::Evaluator::new();
     let mut lexer = lexer::Lexer::new(&input);
     let mut p = parser::Parser::new(lexer);
-    let program = p.parse_program().unwrap();
+    let program = match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => return errors.iter().map(|e| e.render(&input)).collect(),
+    };
     let final_object = evaluator.eval_statements(&program.statements);
     Evaluator::unwrap_object(final_object)
 }
+
+/// Parses `input` and returns its syntax tree as JSON, `{"program": ...}`,
+/// or `{"errors": [{"message": ..., "span": ...}, ...]}` if it doesn't parse.
+#[cfg(feature = "serde")]
+#[wasm_bindgen]
+pub fn parse_to_json(input: String) -> String {
+    use serde_json::json;
+
+    let mut p = parser::Parser::new(lexer::Lexer::new(&input));
+    let result = match p.parse_program() {
+        Ok(program) => json!({ "program": program }),
+        Err(errors) => {
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|e| json!({ "message": e.to_string(), "span": e.span() }))
+                .collect();
+            json!({ "errors": errors })
+        }
+    };
+    result.to_string()
+}
 #[wasm_bindgen]
 extern {
     pub fn alert(s: &str);
@@ -48,7 +74,37 @@ mod tests {
                               }
                             };
                             fibonacci(20)";
-        let ans = i32::from_str(&eval_monkey_program(input.to_string())).unwrap();
+        let ans = i64::from_str(&eval_monkey_program(input.to_string())).unwrap();
         assert_eq!(ans, 6765);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_to_json() {
+        use crate::{ast::Program, lexer, parse_to_json, parser, token::SpannedToken};
+        use serde_json::{json, Value};
+
+        let input = "let x = fn(a) { -a * 2.5 }; x(\"s\")[0]";
+        let json: Value = serde_json::from_str(&parse_to_json(input.to_string())).unwrap();
+        assert_eq!(
+            json["program"]["statements"][0]["LetStatement"][0],
+            json!({ "token": { "Ident": "x" }, "value": "x" })
+        );
+        let program: Program = serde_json::from_value(json["program"].clone()).unwrap();
+        let expected = parser::Parser::new(lexer::Lexer::new(input)).parse_program().unwrap();
+        assert_eq!(program, expected);
+
+        let json: Value = serde_json::from_str(&parse_to_json("let = 1;".to_string())).unwrap();
+        assert_eq!(
+            json,
+            json!({ "errors": [{
+                "message": "expected identifier, found `=`",
+                "span": { "start": 4, "end": 5, "line": 1, "column": 5 },
+            }] })
+        );
+
+        let tokens = lexer::tokenize("a == 1");
+        let json = serde_json::to_string(&tokens).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<SpannedToken>>(&json).unwrap(), tokens);
+    }
 }
\ No newline at end of file
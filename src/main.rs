@@ -1,11 +1,15 @@
 extern crate monkey_lang_rs;
 
-use std::{io::Write, str::FromStr};
+use std::{fs, io::{Read, Write}, process, str::FromStr};
 use monkey_lang_rs::evaluator::{self, Evaluator};
 
-use monkey_lang_rs::{lexer, parser};
+use monkey_lang_rs::{formatter, lexer, parser};
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("fmt") {
+        process::exit(fmt(&args[1..]));
+    }
     let mut evaluator = evaluator::Evaluator::new();
     loop {
         let mut input = String::new();
@@ -15,8 +19,97 @@ fn main() {
         }
         let mut lexer = lexer::Lexer::new(&input);
         let mut p = parser::Parser::new(lexer);
-        let program = p.parse_program().unwrap();
+        let program = match p.parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                for e in errors {
+                    eprint!("{}", e.render(&input));
+                }
+                continue;
+            }
+        };
         evaluator.eval_statements(&program.statements);
         std::io::stdout().flush();
     }
-}
\ No newline at end of file
+}
+
+// `monkey fmt [--check] [FILE]...` formats the files in place, or stdin to
+// stdout. With `--check` nothing is written and the exit code is 1 if some
+// input isn't formatted.
+fn fmt(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with('-') => {
+                eprintln!("error: unknown option `{}`\nusage: monkey fmt [--check] [FILE]...", flag);
+                return 2;
+            }
+            file => files.push(file),
+        }
+    }
+
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut source) {
+            eprintln!("error: can't read stdin: {}", e);
+            return 1;
+        }
+        return match format_source("<stdin>", &source) {
+            Some(formatted) if check && formatted != source => {
+                println!("<stdin> is not formatted");
+                1
+            }
+            Some(_) if check => 0,
+            Some(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            None => 1,
+        };
+    }
+
+    let mut status = 0;
+    for file in files {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: can't read {}: {}", file, e);
+                status = 1;
+                continue;
+            }
+        };
+        let formatted = match format_source(file, &source) {
+            Some(formatted) => formatted,
+            None => {
+                status = 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", file);
+            status = 1;
+        } else if let Err(e) = fs::write(file, formatted) {
+            eprintln!("error: can't write {}: {}", file, e);
+            status = 1;
+        }
+    }
+    status
+}
+
+fn format_source(name: &str, source: &str) -> Option<String> {
+    match formatter::format(source) {
+        Ok(formatted) => Some(formatted),
+        Err(errors) => {
+            eprintln!("{}:", name);
+            for e in errors {
+                eprint!("{}", e.render(source));
+            }
+            None
+        }
+    }
+}
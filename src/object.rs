@@ -1,16 +1,108 @@
+use crate::evaluator::builtins::Builtin;
 use crate::evaluator::env::Env;
 use crate::ast::{Identifier, BlockStatement};
+use std::collections::BTreeMap;
+use num_bigint::BigInt;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Object {
-    Integer(i32),
+    Integer(i64),
+    // Only for values that don't fit in an i64, see `Object::from_big_int`
+    BigInt(BigInt),
+    Float(f64),
     String(String),
     Null,
     Boolean(bool),
     Return(Box<Object>),
+    // Signals from `break` and `continue` on their way out to the loop
+    Break,
+    Continue,
     Function(Vec<Identifier>, BlockStatement, Env),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+    // Start, end and whether the end is included. Only iterated, never stored
+    // as a list.
+    Range(i64, i64, bool),
+    Builtin(Builtin),
+    Error(String),
+}
+
+/// The objects that can be used as hash keys.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone)]
+pub enum HashKey {
+    Integer(i64),
+    BigInt(BigInt),
+    Boolean(bool),
+    String(String),
+}
+
+impl HashKey {
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Integer(x) => Object::Integer(*x),
+            HashKey::BigInt(x) => Object::BigInt(x.clone()),
+            HashKey::Boolean(x) => Object::Boolean(*x),
+            HashKey::String(x) => Object::String(x.clone()),
+        }
+    }
+}
+
+impl Object {
+    /// An `Integer` if `x` fits in an i64, otherwise a `BigInt`.
+    pub fn from_big_int(x: BigInt) -> Object {
+        match i64::try_from(&x) {
+            Ok(x) => Object::Integer(x),
+            Err(_) => Object::BigInt(x),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) | Object::BigInt(_) => "INTEGER",
+            Object::Float(_) => "FLOAT",
+            Object::String(_) => "STRING",
+            Object::Null => "NULL",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::Return(_) => "RETURN_VALUE",
+            Object::Break => "BREAK",
+            Object::Continue => "CONTINUE",
+            Object::Function(..) => "FUNCTION",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+            Object::Range(..) => "RANGE",
+            Object::Builtin(_) => "BUILTIN",
+            Object::Error(_) => "ERROR",
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Object::Error(_))
+    }
+
+    /// `Return`, `Break` and `Continue` carry control flow out to the
+    /// function or loop that handles them, like errors skipping whatever
+    /// expression they come up through.
+    pub fn is_signal(&self) -> bool {
+        matches!(self, Object::Return(_) | Object::Break | Object::Continue)
+    }
+
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(x) => Some(HashKey::Integer(*x)),
+            Object::BigInt(x) => Some(HashKey::BigInt(x.clone())),
+            Object::Boolean(x) => Some(HashKey::Boolean(*x)),
+            Object::String(x) => Some(HashKey::String(x.clone())),
+            _ => None,
+        }
+    }
+
+    /// `hash_key`, or the runtime error for using this object as a key.
+    pub fn to_hash_key(&self) -> Result<HashKey, Object> {
+        self.hash_key()
+            .ok_or_else(|| Object::Error(format!("unusable as hash key: {}", self.type_name())))
+    }
 }
 
 pub struct ReturnValue {
     value: Object
-}
\ No newline at end of file
+}
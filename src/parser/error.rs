@@ -0,0 +1,102 @@
+use crate::lexer::LexError;
+use crate::token::{Span, Token};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    /// The grammar required `expected` but the source had `found`.
+    UnexpectedToken { expected: Token, found: Token, span: Span },
+    /// A closing `)` or `}` is missing.
+    MissingDelimiter { delimiter: Token, found: Token, span: Span },
+    /// A name was required, e.g. after `let`.
+    ExpectedIdentifier { found: Token, span: Span },
+    /// Function parameters must be a comma separated list of identifiers.
+    InvalidParameterList { found: Token, span: Span },
+    /// No expression can start with `found`.
+    NoPrefixParse { found: Token, span: Span },
+    /// The left side of `=` (or `+=` etc.) isn't a name.
+    InvalidAssignmentTarget { operator: Token, span: Span },
+    /// The lexer couldn't make a token out of the input.
+    Lex(LexError),
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::MissingDelimiter { span, .. }
+            | ParseError::ExpectedIdentifier { span, .. }
+            | ParseError::InvalidParameterList { span, .. }
+            | ParseError::NoPrefixParse { span, .. }
+            | ParseError::InvalidAssignmentTarget { span, .. } => *span,
+            ParseError::Lex(err) => err.span(),
+        }
+    }
+
+    /// Renders the error together with the offending source line and a caret
+    /// under the span, e.g.
+    ///
+    /// ```text
+    /// error: expected `=`, found `5`
+    ///  --> 1:7
+    ///   |
+    /// 1 | let x 5;
+    ///   |       ^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let span = self.span();
+        let line = source.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+        let gutter = " ".repeat(span.line.to_string().len());
+        let width = source.get(span.start..span.end).map_or(0, |s| s.chars().count());
+        let carets = "^".repeat(width.max(1));
+        format!(
+            "error: {}\n{}--> {}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self,
+            gutter,
+            span.line,
+            span.column,
+            gutter,
+            span.line,
+            line,
+            gutter,
+            " ".repeat(span.column.saturating_sub(1)),
+            carets
+        )
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::EOF => "end of input".to_string(),
+        Token::Illegal => "illegal token".to_string(),
+        _ => format!("`{}`", token),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { expected, found, .. } => {
+                write!(f, "expected {}, found {}", describe(expected), describe(found))
+            }
+            ParseError::MissingDelimiter { delimiter, found, .. } => {
+                write!(f, "missing closing {}, found {}", describe(delimiter), describe(found))
+            }
+            ParseError::ExpectedIdentifier { found, .. } => {
+                write!(f, "expected identifier, found {}", describe(found))
+            }
+            ParseError::InvalidParameterList { found, .. } => {
+                write!(f, "invalid parameter list, unexpected {}", describe(found))
+            }
+            ParseError::NoPrefixParse { found, .. } => {
+                write!(f, "expected expression, found {}", describe(found))
+            }
+            ParseError::InvalidAssignmentTarget { operator, .. } => {
+                write!(f, "can only assign to a name with {}", describe(operator))
+            }
+            ParseError::Lex(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
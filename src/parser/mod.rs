@@ -1,47 +1,116 @@
+mod error;
+
+pub use error::ParseError;
+
 use crate::{
     ast::{self, Expression, Identifier, Statement, Sticky},
+    cst::{self, SyntaxKind},
     lexer::Lexer,
-    token::Token,
+    token::{Span, Token},
 };
 
 #[derive(Debug)]
 pub struct Parser<'a> {
     l: Lexer<'a>,
     current_token: Token,
+    current_span: Span,
     peek_token: Token,
-    errors: Vec<String>,
+    peek_span: Span,
+    errors: Vec<ParseError>,
+    // Only set when building a concrete syntax tree
+    cst: Option<cst::Builder>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(l: Lexer<'a>) -> Parser<'a> {
+        Self::build(l, None)
+    }
+
+    /// A parser that also records a concrete syntax tree, see `cst::parse`.
+    pub(crate) fn with_cst(l: Lexer<'a>) -> Parser<'a> {
+        Self::build(l, Some(cst::Builder::default()))
+    }
+
+    fn build(l: Lexer<'a>, cst: Option<cst::Builder>) -> Parser<'a> {
         let mut p = Parser {
             l,
             current_token: Token::EOF,
+            current_span: Span::default(),
             peek_token: Token::EOF,
+            peek_span: Span::default(),
             errors: vec![],
+            cst,
         };
         p.next_token();
         p.next_token();
         p
     }
 
+    pub(crate) fn take_cst(&mut self) -> Option<cst::Builder> {
+        self.cst.take()
+    }
+
+    // Concrete syntax tree nodes span from the token the parser is on when
+    // they start to the one it is on when they finish.
+    fn token_index(&self) -> usize {
+        self.cst.as_ref().map_or(0, |cst| cst.current())
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) -> usize {
+        self.start_node_at(self.token_index(), kind)
+    }
+
+    fn start_node_at(&mut self, start: usize, kind: SyntaxKind) -> usize {
+        match &mut self.cst {
+            Some(cst) => cst.start_node(kind, start),
+            None => 0,
+        }
+    }
+
+    fn finish_node(&mut self, node: usize) {
+        let end = self.token_index();
+        if let Some(cst) = &mut self.cst {
+            cst.finish_node(node, end);
+        }
+    }
+
+    // Statements take their `;` along, even though it's parsed on its own
+    fn finish_statement(&mut self, node: usize) {
+        let end = self.token_index() + usize::from(self.peek_token == Token::Semicolon);
+        if let Some(cst) = &mut self.cst {
+            cst.finish_node(node, end);
+        }
+    }
+
     fn parse_expression_statement(&mut self) -> Option<ast::Statement> {
         if let Some(expr) = self.parse_expression(Sticky::LOWEST) {
-            let stmt = Statement::ExpressionStatement(expr);
-            println!("Statement: {:?}", stmt);
-            Some(stmt)
+            Some(Statement::ExpressionStatement(expr))
         } else {
             None
         }
     }
 
-    fn exp_to_sticky(token: &Token) -> Sticky {
+    pub(crate) fn exp_to_sticky(token: &Token) -> Sticky {
         match token {
             Token::LParen => Sticky::CALL,
+            Token::LBracket => Sticky::INDEX,
             Token::Plus | Token::Minus => Sticky::SUM,
-            Token::Asterisk | Token::Slash => Sticky::PRODUCT,
-            Token::Lt | Token::Gt => Sticky::LESSGREATER,
+            Token::Asterisk | Token::Slash | Token::Percent => Sticky::PRODUCT,
+            Token::Power => Sticky::POWER,
+            Token::Lt | Token::Gt | Token::LtEq | Token::GtEq => Sticky::LESSGREATER,
+            Token::Pipe => Sticky::BITOR,
+            Token::Caret => Sticky::BITXOR,
+            Token::Ampersand => Sticky::BITAND,
+            Token::Shl | Token::Shr => Sticky::SHIFT,
             Token::Eq | Token::NotEq => Sticky::EQUALS,
+            Token::And => Sticky::AND,
+            Token::Or => Sticky::OR,
+            Token::DotDot | Token::DotDotEq => Sticky::RANGE,
+            Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::AsteriskAssign
+            | Token::SlashAssign => Sticky::ASSIGN,
             _ => Sticky::LOWEST,
         }
     }
@@ -58,7 +127,11 @@ impl<'a> Parser<'a> {
 
     fn parse_infix_expression(&mut self, left: ast::Expression) -> Option<ast::Expression> {
         let operator = self.current_token.clone();
-        let stick = self.cur_stickiness();
+        // `**` is right associative: the right side takes in further `**`s
+        let stick = match operator {
+            Token::Power => Sticky::PREFIX,
+            _ => self.cur_stickiness(),
+        };
         self.next_token();
         match self.parse_expression(stick) {
             Some(right) => Some(Expression::Infix(operator, Box::new(left), Box::new(right))),
@@ -66,6 +139,22 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // `x = value`, `x += value` etc. Assignment is right associative, so the
+    // value takes in further assignments: `a = b = 1` sets both.
+    fn parse_assign_expression(&mut self, left: ast::Expression) -> Option<ast::Expression> {
+        let operator = self.current_token.clone();
+        let name = match left {
+            Expression::Ident(value) => Identifier { token: Token::Ident(value.clone()), value },
+            _ => {
+                self.log_error(ParseError::InvalidAssignmentTarget { operator, span: self.current_span });
+                return None;
+            }
+        };
+        self.next_token();
+        let value = self.parse_expression(Sticky::LOWEST)?;
+        Some(Expression::Assign(operator, name, Box::new(value)))
+    }
+
     fn parse_bool_literal(&mut self) -> Option<ast::Expression> {
         if let Token::True = self.current_token {
             Some(ast::Expression::Boolean(true))
@@ -76,14 +165,13 @@ impl<'a> Parser<'a> {
 
     fn parse_grouped_expression(&mut self) -> Option<ast::Expression> {
         self.next_token();
-        let expr = self.parse_expression(Sticky::LOWEST);
+        let expr = self.parse_expression(Sticky::LOWEST)?;
         if Token::RParen.ne(&self.peek_token) {
-            self.next_token();
-            self.next_token();
+            self.peek_error(Token::RParen);
             None
         } else {
             self.next_token();
-            expr
+            Some(expr)
         }
     }
 
@@ -104,7 +192,6 @@ impl<'a> Parser<'a> {
                     None
                 }
             } else {
-                self.log_error("Invalid IF condition".to_string());
                 None
             }
         } else {
@@ -114,25 +201,37 @@ impl<'a> Parser<'a> {
 
     fn parse_block_expression(&mut self) -> Option<ast::BlockStatement> {
         if self.current_token == Token::LBrace {
+            let node = self.start_node(SyntaxKind::Block);
             self.next_token();
             let mut block = ast::BlockStatement { statements: vec![] };
             loop {
                 match self.current_token {
                     Token::RBrace | Token::EOF => break,
-                    _ => match self.parse_statement() {
-                        Some(x) => block.statements.push(x),
-                        None => {}
-                    },
+                    _ => self.parse_statement_or_recover(&mut block.statements),
                 }
                 self.next_token();
             }
+            if self.current_token == Token::EOF {
+                self.log_error(ParseError::MissingDelimiter {
+                    delimiter: Token::RBrace,
+                    found: Token::EOF,
+                    span: self.current_span,
+                });
+            }
+            self.finish_node(node);
             Some(block)
         } else {
+            self.log_error(ParseError::UnexpectedToken {
+                expected: Token::LBrace,
+                found: self.current_token.clone(),
+                span: self.current_span,
+            });
             None
         }
     }
 
-    fn parse_function_params(&mut self) -> Vec<ast::Identifier> {
+    // Leaves the parser on the closing `)` of the parameter list.
+    fn parse_function_params(&mut self) -> Option<Vec<ast::Identifier>> {
         let mut identifiers: Vec<ast::Identifier> = vec![];
         if self.current_token != Token::RParen {
             loop {
@@ -144,37 +243,56 @@ impl<'a> Parser<'a> {
                     identifiers.push(iden);
                     match self.peek_token {
                         Token::Comma => self.expect_peek(Token::Comma),
-                        Token::RParen => break,
-                        _ => {
-                            self.log_error("Unexpected token found while parsing function parameters".to_string());
+                        Token::RParen => {
+                            self.next_token();
                             break;
                         }
+                        _ => {
+                            self.log_error(ParseError::InvalidParameterList {
+                                found: self.peek_token.clone(),
+                                span: self.peek_span,
+                            });
+                            return None;
+                        }
                     };
                 } else {
-                    self.log_error("Params are not valid identifiers!".to_string());
+                    self.log_error(ParseError::InvalidParameterList {
+                        found: self.current_token.clone(),
+                        span: self.current_span,
+                    });
+                    return None;
                 }
             }
         }
-        identifiers
+        Some(identifiers)
     }
 
     fn parse_function(&mut self) -> Option<ast::Expression> {
         //fn(x, y) { x + y; }
         if self.expect_peek(Token::LParen) {
-            let params = self.parse_function_params();
-            if self.expect_peek(Token::RParen) {
-                let block = self.parse_block_expression();
-                Some(Expression::Function(params, block))
-            } else {
-                None
-            }
+            let params = self.parse_function_params()?;
+            self.next_token();
+            let block = self.parse_block_expression();
+            Some(Expression::Function(params, block))
         } else {
             None
         }
     }
 
     fn parse_expression(&mut self, stick: Sticky) -> Option<ast::Expression> {
-        let mut left = match self.current_token {
+        let start = self.token_index();
+        let kind = match self.current_token {
+            Token::Ident(_) => SyntaxKind::Name,
+            Token::Function => SyntaxKind::Function,
+            Token::If => SyntaxKind::If,
+            Token::Bang | Token::Minus | Token::Plus | Token::Tilde => SyntaxKind::Prefix,
+            Token::LParen => SyntaxKind::Paren,
+            Token::LBracket => SyntaxKind::Array,
+            Token::LBrace => SyntaxKind::Hash,
+            _ => SyntaxKind::Literal,
+        };
+        let node = self.start_node(kind);
+        let left = match self.current_token {
             Token::Function => self.parse_function(),
             Token::Ident(_) => self.parse_identifier(),
             Token::If => self.parse_if_expression(),
@@ -182,7 +300,9 @@ impl<'a> Parser<'a> {
                 let left_exp = self.parse_integer_literal();
                 left_exp
             }
-            Token::Bang | Token::Minus => {
+            Token::Float(x) => Some(ast::Expression::Float(x)),
+            Token::Str(ref x) => Some(ast::Expression::Str(x.to_string())),
+            Token::Bang | Token::Minus | Token::Plus | Token::Tilde => {
                 let operator = self.current_token.clone();
                 self.next_token();
                 let right_exp = self.parse_expression(Sticky::PREFIX);
@@ -201,22 +321,40 @@ impl<'a> Parser<'a> {
                 let left_exp = self.parse_grouped_expression();
                 left_exp
             }
+            Token::LBracket => {
+                self.next_token();
+                let elements = self.parse_expression_list(Token::RBracket)?;
+                Some(ast::Expression::Array(elements))
+            }
+            Token::LBrace => self.parse_hash_literal(),
+            // Already reported by the lexer
+            Token::Illegal => None,
             _ => {
-                self.log_error("Unknown expression".to_string());
+                self.log_error(ParseError::NoPrefixParse {
+                    found: self.current_token.clone(),
+                    span: self.current_span,
+                });
                 None
             }
         };
         // println!("LEFT: {:?}, current: {:?}, next: {:?}", left, self.current_token, self.peek_token);
+        let mut left = left?;
+        self.finish_node(node);
         /*
         infix stuff
          */
         while self.peek_token != Token::Semicolon && stick < self.peek_stickiness() {
+            let node = self.start_node_at(start, Self::infix_kind(&self.peek_token));
             match self.peek_token {
                 Token::LParen => {
                     // println!("MID: {:?}, current: {:?}, next: {:?}", left, self.current_token, self.peek_token);
                     self.expect_peek(Token::LParen);
                     // println!("MID2: {:?}, current: {:?}, next: {:?}", left, self.current_token, self.peek_token);
-                    left = self.parse_call_arguments(left.unwrap());
+                    left = self.parse_call_arguments(left)?;
+                }
+                Token::LBracket => {
+                    self.expect_peek(Token::LBracket);
+                    left = self.parse_index_expression(left)?;
                 }
                 Token::Asterisk
                 | Token::Plus
@@ -225,48 +363,119 @@ impl<'a> Parser<'a> {
                 | Token::Eq
                 | Token::NotEq
                 | Token::Gt
-                | Token::Lt => {
+                | Token::Lt
+                | Token::And
+                | Token::Or
+                | Token::Percent
+                | Token::Power
+                | Token::LtEq
+                | Token::GtEq
+                | Token::Ampersand
+                | Token::Pipe
+                | Token::Caret
+                | Token::Shl
+                | Token::Shr => {
                     self.next_token();
-                    left = self.parse_infix_expression(left.unwrap());
+                    left = self.parse_infix_expression(left)?;
                 }
-                _ => left = left,
+                Token::Assign
+                | Token::PlusAssign
+                | Token::MinusAssign
+                | Token::AsteriskAssign
+                | Token::SlashAssign => {
+                    self.next_token();
+                    left = self.parse_assign_expression(left)?;
+                }
+                Token::DotDot | Token::DotDotEq => {
+                    self.next_token();
+                    let inclusive = self.current_token == Token::DotDotEq;
+                    self.next_token();
+                    let end = self.parse_expression(Sticky::RANGE)?;
+                    left = Expression::Range(Box::new(left), Box::new(end), inclusive);
+                }
+                _ => break,
             }
+            self.finish_node(node);
         }
         // println!("RIGHT: {:?}\tcurrent: {:?}\tnext: {:?}", left, self.current_token, self.peek_token);
-        left
+        Some(left)
+    }
+
+    fn infix_kind(token: &Token) -> SyntaxKind {
+        match token {
+            Token::LParen => SyntaxKind::Call,
+            Token::LBracket => SyntaxKind::Index,
+            Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::AsteriskAssign
+            | Token::SlashAssign => SyntaxKind::Assign,
+            Token::DotDot | Token::DotDotEq => SyntaxKind::Range,
+            _ => SyntaxKind::Infix,
+        }
     }
 
     fn parse_call_arguments(&mut self, identifier: ast::Expression) -> Option<ast::Expression> {
-        // println!("\t\t\tCALL");
-        let mut arguments: Vec<ast::Expression> = vec![];
-        loop {
-            match &self.current_token {
-                Token::Comma => { self.expect_peek(Token::Comma); }
-                Token::RParen => {
-                    // println!("RPAREN!!!");
-                    break
-                },
+        let arguments = self.parse_expression_list(Token::RParen)?;
+        Some(ast::Expression::Call(Box::new(identifier), arguments))
+    }
+
+    // {"key": value, ...}, leaves the parser on the closing `}`.
+    fn parse_hash_literal(&mut self) -> Option<ast::Expression> {
+        let mut pairs = vec![];
+        self.next_token();
+        while self.current_token != Token::RBrace {
+            let key = self.parse_expression(Sticky::LOWEST)?;
+            if !self.expect_peek(Token::Colon) {
+                return None;
+            }
+            let value = self.parse_expression(Sticky::LOWEST)?;
+            pairs.push((key, value));
+            match self.peek_token {
+                Token::Comma => self.expect_peek(Token::Comma),
+                Token::RBrace => {
+                    self.next_token();
+                    break;
+                }
                 _ => {
-                    // println!("{:?}\tcurrent: {:?}\tnext: {:?}", arguments, self.current_token, self.peek_token);
-                    if let Some(expr) = self.parse_expression(Sticky::LOWEST) {
-                        arguments.push(expr);
-                        match self.peek_token {
-                            Token::Comma => {
-                                self.expect_peek(Token::Comma);
-                            }
-                            _ => {
-                                self.next_token();
-                            }
-                        }
-                        // println!("Pushed {:?}\tcurrent: {:?}\tnext: {:?}", arguments, self.current_token, self.peek_token);
-                    } else {
-                        self.log_error("Error parsing call arguments, invalid expression".to_string());
-                    }
+                    self.peek_error(Token::RBrace);
+                    return None;
                 }
             };
-            // println!("loop done{:?}\tcurrent: {:?}\tnext: {:?}", arguments, self.current_token, self.peek_token);
         }
-        Some(ast::Expression::Call(Box::new(identifier), arguments))
+        Some(ast::Expression::Hash(pairs))
+    }
+
+    // Called with the parser on the first token inside the `[`.
+    fn parse_index_expression(&mut self, left: ast::Expression) -> Option<ast::Expression> {
+        let index = self.parse_expression(Sticky::LOWEST)?;
+        if self.peek_token != Token::RBracket {
+            self.peek_error(Token::RBracket);
+            return None;
+        }
+        self.next_token();
+        Some(ast::Expression::Index(Box::new(left), Box::new(index)))
+    }
+
+    // Comma separated expressions up to `end`, starting from the first token
+    // after the opening delimiter. Leaves the parser on `end`.
+    fn parse_expression_list(&mut self, end: Token) -> Option<Vec<ast::Expression>> {
+        let mut list: Vec<ast::Expression> = vec![];
+        if self.current_token != end {
+            loop {
+                list.push(self.parse_expression(Sticky::LOWEST)?);
+                if self.peek_token == Token::Comma {
+                    self.expect_peek(Token::Comma);
+                } else if self.peek_token == end {
+                    self.next_token();
+                    break;
+                } else {
+                    self.peek_error(end);
+                    return None;
+                }
+            }
+        }
+        Some(list)
     }
 
     fn parse_identifier(&self) -> Option<ast::Expression> {
@@ -286,13 +495,27 @@ impl<'a> Parser<'a> {
     }
 
     fn next_token(&mut self) {
-        self.current_token = self.peek_token.to_owned();
-        self.peek_token = self.l.next_token();
+        let next = loop {
+            let next = self.l.next_spanned_token();
+            if !next.token.is_trivia() {
+                break next;
+            }
+            if let Some(cst) = &mut self.cst {
+                cst.push_trivia(next);
+            }
+        };
+        if let Some(cst) = &mut self.cst {
+            cst.push_token(next.clone());
+        }
+        for err in self.l.take_errors() {
+            self.log_error(ParseError::Lex(err));
+        }
+        self.current_token = std::mem::replace(&mut self.peek_token, next.token);
+        self.current_span = std::mem::replace(&mut self.peek_span, next.span);
     }
 
     fn parse_statement(&mut self) -> Option<ast::Statement> {
         match self.current_token {
-            Token::Illegal => None,
             Token::Semicolon => None,
             Token::Let => {
                 self.next_token();
@@ -302,33 +525,32 @@ impl<'a> Parser<'a> {
                         token: var.clone(),
                         value: val.to_string(),
                     };
-                    self.expect_peek(Token::Assign);
+                    if !self.expect_peek(Token::Assign) {
+                        return None;
+                    }
                     if let Some(expr) = self.parse_expression(Sticky::LOWEST) {
                         Some(Statement::LetStatement(iden, expr))
                     } else {
                         None
                     }
                 } else {
-                    self.log_error(Parser::<'a>::peek_error(
-                        &Token::Ident("IDEN".to_string()),
-                        &self.peek_token,
-                    ));
+                    self.log_error(ParseError::ExpectedIdentifier {
+                        found: self.current_token.clone(),
+                        span: self.current_span,
+                    });
                     None
                 }
             }
             Token::Return => {
                 self.next_token();
                 if let Some(expr) = self.parse_expression(Sticky::LOWEST) {
-                    loop {
-                        if self.current_token != Token::Semicolon {
-                            self.next_token();
-                        } else {
-                            break;
-                        }
-                    }
                     Some(Statement::ReturnStatement(expr))
                 } else { None }
             }
+            Token::While => self.parse_while_statement(),
+            Token::For => self.parse_for_statement(),
+            Token::Break => Some(Statement::BreakStatement),
+            Token::Continue => Some(Statement::ContinueStatement),
             _ => {
                 // //parse expr
                 self.parse_expression_statement()
@@ -336,7 +558,110 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn log_error(&mut self, err: String) {
+    // while (condition) { body }, leaves the parser on the closing `}`.
+    fn parse_while_statement(&mut self) -> Option<ast::Statement> {
+        if !self.expect_peek(Token::LParen) {
+            return None;
+        }
+        let condition = self.parse_expression(Sticky::LOWEST)?;
+        if !self.expect_peek(Token::RParen) {
+            return None;
+        }
+        let body = self.parse_block_expression()?;
+        Some(Statement::WhileStatement(condition, body))
+    }
+
+    // for (name in iterable) { body } or for (key, value in iterable) { body },
+    // leaves the parser on the closing `}`.
+    fn parse_for_statement(&mut self) -> Option<ast::Statement> {
+        if !self.expect_peek(Token::LParen) {
+            return None;
+        }
+        let name = self.parse_name()?;
+        let value = if self.peek_token == Token::Comma {
+            self.expect_peek(Token::Comma);
+            Some(self.parse_name()?)
+        } else {
+            None
+        };
+        if !self.expect_peek(Token::In) {
+            return None;
+        }
+        let iterable = self.parse_expression(Sticky::LOWEST)?;
+        if !self.expect_peek(Token::RParen) {
+            return None;
+        }
+        let body = self.parse_block_expression()?;
+        Some(Statement::ForStatement(name, value, iterable, body))
+    }
+
+    fn parse_name(&mut self) -> Option<Identifier> {
+        match &self.current_token {
+            Token::Ident(value) => Some(Identifier { token: self.current_token.clone(), value: value.clone() }),
+            _ => {
+                self.log_error(ParseError::ExpectedIdentifier {
+                    found: self.current_token.clone(),
+                    span: self.current_span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Parses one statement into `statements`. If that produced errors, skips
+    /// ahead so the next token starts a fresh statement: past a `;`, or up to
+    /// a `}`, a keyword that starts a statement or the end of input.
+    fn parse_statement_or_recover(&mut self, statements: &mut Vec<ast::Statement>) {
+        let errors_before = self.errors.len();
+        let node = self.start_node(match self.current_token {
+            Token::Let => SyntaxKind::LetStatement,
+            Token::Return => SyntaxKind::ReturnStatement,
+            Token::While => SyntaxKind::WhileStatement,
+            Token::For => SyntaxKind::ForStatement,
+            Token::Break => SyntaxKind::BreakStatement,
+            Token::Continue => SyntaxKind::ContinueStatement,
+            _ => SyntaxKind::ExpressionStatement,
+        });
+        if let Some(x) = self.parse_statement() {
+            self.finish_statement(node);
+            statements.push(x);
+        }
+        if self.errors.len() > errors_before {
+            self.synchronize();
+        }
+    }
+
+    // Blocks opened while skipping are skipped whole, so a `let` inside a
+    // broken function body doesn't count as a synchronization point.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.current_token {
+                Token::EOF => break,
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth > 0 => depth -= 1,
+                Token::Semicolon if depth == 0 => break,
+                _ => {}
+            }
+            match self.peek_token {
+                Token::EOF => break,
+                Token::RBrace
+                | Token::Let
+                | Token::Return
+                | Token::While
+                | Token::For
+                | Token::Break
+                | Token::Continue
+                    if depth == 0 =>
+                {
+                    break
+                }
+                _ => self.next_token(),
+            }
+        }
+    }
+
+    pub fn log_error(&mut self, err: ParseError) {
         self.errors.push(err);
     }
 
@@ -346,24 +671,28 @@ impl<'a> Parser<'a> {
             self.next_token();
             true
         } else {
-            self.log_error(Self::peek_error(&expected_token, &self.peek_token));
+            self.peek_error(expected_token);
             false
         }
     }
 
-    pub fn peek_error(expected: &Token, received: &Token) -> String {
-        format!("Expected {:?}, Got {:?}", expected, received).to_string()
+    fn peek_error(&mut self, expected: Token) {
+        let found = self.peek_token.clone();
+        let span = self.peek_span;
+        let err = match expected {
+            Token::RParen | Token::RBrace | Token::RBracket => ParseError::MissingDelimiter { delimiter: expected, found, span },
+            _ => ParseError::UnexpectedToken { expected, found, span },
+        };
+        self.log_error(err);
     }
-    pub fn parse_program(&mut self) -> Result<ast::Program, Vec<String>> {
+
+    pub fn parse_program(&mut self) -> Result<ast::Program, Vec<ParseError>> {
         let mut program = ast::Program { statements: vec![] };
 
         loop {
             match self.current_token {
                 Token::EOF => break,
-                _ => match self.parse_statement() {
-                    Some(x) => program.statements.push(x),
-                    None => {}
-                },
+                _ => self.parse_statement_or_recover(&mut program.statements),
             }
             self.next_token();
         }
@@ -377,9 +706,9 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ast::Statement, lexer};
+    use crate::{ast::{Expression, Statement}, lexer, token::Token};
 
-    use super::Parser;
+    use super::{ParseError, Parser};
 
     #[test]
     fn test_let_statement() {
@@ -408,13 +737,47 @@ mod tests {
         match program {
             Ok(_) => panic!("Catching errors failed!"),
             Err(x) => {
-                for s in x {
-                    println!("{:?}", s)
+                for s in x.iter() {
+                    println!("{}", s.render(input))
                 }
+                assert!(matches!(
+                    x[0],
+                    ParseError::UnexpectedToken { expected: Token::Assign, found: Token::Int(5), .. }
+                ));
             }
         }
     }
 
+    #[test]
+    fn test_error_spans() {
+        let tests = vec![
+            ("let x 5;", "expected `=`, found `5`", (1, 7)),
+            ("let = 10;", "expected identifier, found `=`", (1, 5)),
+            ("let a = 1;\nlet b = (1 + 2;", "missing closing `)`, found `;`", (2, 15)),
+            ("fn(x, 1) { x }", "invalid parameter list, unexpected `1`", (1, 7)),
+            ("if (x) { x", "missing closing `}`, found end of input", (1, 11)),
+            ("let a = 1;\na + 1 = 2;", "can only assign to a name with `=`", (2, 7)),
+            ("a[0] -= 1", "can only assign to a name with `-=`", (1, 6)),
+        ];
+        for (input, message, (line, column)) in tests {
+            let mut p = Parser::new(lexer::Lexer::new(input));
+            let errors = p.parse_program().expect_err("Catching errors failed!");
+            assert_eq!(errors[0].to_string(), message, "for input {:?}", input);
+            assert_eq!((errors[0].span().line, errors[0].span().column), (line, column), "for input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_render() {
+        let input = "let a = 1;\nlet b = (1 + 2;";
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().err().unwrap();
+        assert_eq!(
+            errors[0].render(input),
+            "error: missing closing `)`, found `;`\n --> 2:15\n  |\n2 | let b = (1 + 2;\n  |               ^\n"
+        );
+    }
+
     #[test]
     fn test_return_statements() {
         let input = "
@@ -508,22 +871,126 @@ mod tests {
     #[test]
     fn test_operator_precedence() {
         let tests = vec![
-            ("1 + (2 + 3) + 4", "((1+ (2+3)) + 4 )"),
+            ("-a * b", "((-a) * b)"),
+            ("!-a", "(!(-a))"),
+            ("a + b + c", "((a + b) + c)"),
+            ("a + b - c", "((a + b) - c)"),
+            ("a * b * c", "((a * b) * c)"),
+            ("a * b / c", "((a * b) / c)"),
+            ("a + b / c", "(a + (b / c))"),
+            ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
+            ("3 + 4; -5 * 5", "(3 + 4);\n((-5) * 5)"),
+            ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
+            ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
+            ("3 + 4 * 5 == 3 * 1 + 4 * 5", "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))"),
+            ("3 > 5 == false", "((3 > 5) == false)"),
+            ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
             ("(5 + 5) * 2", "((5 + 5) * 2)"),
             ("2 / (5 + 5)", "(2 / (5 + 5))"),
             ("-(5 + 5)", "(-(5 + 5))"),
             ("!(true == true)", "(!(true == true))"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            ("add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))", "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))"),
+            ("a * [1, 2, 3, 4][b * c] * d", "((a * ([1, 2, 3, 4][(b * c)])) * d)"),
+            ("add(a * b[2], b[1], 2 * [1, 2][1])", "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))"),
+            ("-a[0]", "(-(a[0]))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b || c && d", "((a && b) || (c && d))"),
+            ("a == b && c < d || !e", "(((a == b) && (c < d)) || (!e))"),
+            ("f(a || b) && c[0]", "(f((a || b)) && (c[0]))"),
+            ("a + b % c * d", "(a + ((b % c) * d))"),
+            ("a <= b == c >= d", "((a <= b) == (c >= d))"),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+            ("-2 ** 2", "(-(2 ** 2))"),
+            ("2 ** -1 * 3", "((2 ** (-1)) * 3)"),
+            ("a * b ** c[0]", "(a * (b ** (c[0])))"),
+            ("+a - ~b", "((+a) - (~b))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b << c + d", "(a & (b << (c + d)))"),
+            ("a >> 1 < b | c", "((a >> 1) < (b | c))"),
+            ("a == b & c || d", "((a == (b & c)) || d)"),
+            ("x = y = 1 + 2", "(x = (y = (1 + 2)))"),
+            ("x += a || b", "(x += (a || b))"),
+            ("f(x -= 1) * (y /= 2)", "(f((x -= 1)) * (y /= 2))"),
+            ("x *= fn(a) { a = a + 1 }", "(x *= fn(a) { (a = (a + 1)) })"),
+            ("0..n + 1", "(0..(n + 1))"),
+            ("a || b..=c && d", "((a || b)..=(c && d))"),
+            ("x = 1..2..3", "(x = ((1..2)..3))"),
+            ("-1..f(2)[0]", "((-1)..(f(2)[0]))"),
         ];
-        for test in tests {
-            let l1 = lexer::Lexer::new(test.0);
-            let l2 = lexer::Lexer::new(test.1);
-            let mut p1 = Parser::new(l1);
-            let mut p2 = Parser::new(l2);
-            let program1 = p1.parse_program().unwrap();
-            let program2 = p2.parse_program().unwrap();
-            for i in 0..program1.statements.len() {
-                assert!(program1.statements[i] == program2.statements[i])
+        for (input, expected) in tests {
+            let mut p = Parser::new(lexer::Lexer::new(input));
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let input = "let x = 5; return x;
+            let f = fn(a, b) { let c = a + b; c * 2.5 };
+            if (f(1, 2) > 3) { \"big\\n\" } else { };
+            {\"a\": [1, 2][0], true: !false}[\"a\"]
+            fn() { }";
+        let program = Parser::new(lexer::Lexer::new(input)).parse_program().unwrap();
+        assert_eq!(
+            program.to_string(),
+            "let x = 5;\nreturn x;\nlet f = fn(a, b) { let c = (a + b); (c * 2.5) };\n\
+             if ((f(1, 2) > 3)) { \"big\\n\" } else { };\n\
+             ({\"a\": ([1, 2][0]), true: (!false)}[\"a\"]);\nfn() { }"
+        );
+        let printed = program.to_string();
+        let reparsed = Parser::new(lexer::Lexer::new(&printed)).parse_program().unwrap();
+        assert_eq!(reparsed, program);
+    }
+
+    #[test]
+    fn test_while_statement() {
+        let input = "while (i < 10) { if (i == 5) { break; }; i += 1; continue }";
+        let program = Parser::new(lexer::Lexer::new(input)).parse_program().unwrap();
+        let (condition, body) = match &program.statements[..] {
+            [Statement::WhileStatement(condition, body)] => (condition, body),
+            other => panic!("not a while statement: {:?}", other),
+        };
+        assert_eq!(condition.to_string(), "(i < 10)");
+        assert_eq!(body.statements.len(), 3);
+        assert_eq!(body.statements[2], Statement::ContinueStatement);
+        assert_eq!(
+            program.to_string(),
+            "while ((i < 10)) { if ((i == 5)) { break; }; (i += 1); continue; }"
+        );
+
+        let errors = Parser::new(lexer::Lexer::new("while x { }")).parse_program().unwrap_err();
+        assert_eq!(errors[0].to_string(), "expected `(`, found `x`");
+    }
+
+    #[test]
+    fn test_for_statement() {
+        let program = Parser::new(lexer::Lexer::new("for (x in 0..10) { puts(x) }")).parse_program().unwrap();
+        match &program.statements[..] {
+            [Statement::ForStatement(name, None, iterable, body)] => {
+                assert_eq!(name.value, "x");
+                let range = Expression::Range(Box::new(Expression::Integer(0)), Box::new(Expression::Integer(10)), false);
+                assert_eq!(*iterable, range);
+                assert_eq!(body.statements.len(), 1);
             }
+            other => panic!("not a for statement: {:?}", other),
+        }
+
+        let input = "for (k, v in {\"a\": 1}) { if (v > 1) { continue } k }";
+        let program = Parser::new(lexer::Lexer::new(input)).parse_program().unwrap();
+        assert_eq!(program.to_string(), "for (k, v in {\"a\": 1}) { if ((v > 1)) { continue; }; k }");
+
+        let tests = vec![
+            ("for x in y { }", "expected `(`, found `x`"),
+            ("for (1 in y) { }", "expected identifier, found `1`"),
+            ("for (k, in y) { }", "expected identifier, found `in`"),
+            ("for (x y) { }", "expected `in`, found `y`"),
+            ("for (x in y { }", "missing closing `)`, found `{`"),
+        ];
+        for (input, message) in tests {
+            let errors = Parser::new(lexer::Lexer::new(input)).parse_program().unwrap_err();
+            assert_eq!(errors[0].to_string(), message, "for input {:?}", input);
         }
     }
 
@@ -586,4 +1053,136 @@ mod tests {
             "Not an expression statement!"
         )
     }
+
+    #[test]
+    fn test_error_recovery() {
+        let input = "let x 5;
+let y = 10;
+let = 3;
+let z = (1 + 2;
+let f = fn(1) { x };
+let g = fn(x) { let = 1; x };
+if (x { let a = 1; }
+let w = 4;
+while (x) { let = 1 }
+return";
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().expect_err("Catching errors failed!");
+        for e in errors.iter() {
+            println!("{}", e.render(input));
+        }
+        let lines: Vec<usize> = errors.iter().map(|e| e.span().line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5, 6, 7, 9, 10]);
+        assert!(matches!(errors[1], ParseError::ExpectedIdentifier { .. }));
+        assert!(matches!(errors[3], ParseError::InvalidParameterList { found: Token::Int(1), .. }));
+        assert!(matches!(errors[6], ParseError::ExpectedIdentifier { found: Token::Assign, .. }));
+        assert!(matches!(errors[7], ParseError::NoPrefixParse { found: Token::EOF, .. }));
+    }
+
+    #[test]
+    fn test_parser_terminates() {
+        let fragments = [
+            "let", "x", "=", "5", ";", "fn", "(", ")", "{", "}", ",", "if", "else", "return",
+            "while", "break", "continue", "for", "in", "..", "..=", ".",
+            "+", "-", "*", "/", "!", "<", ">", "==", "!=", "true", "false", "@", "\n",
+            "\"s\"", "\"", "\\", "[", "]", ":", "//", "/*", "*/", "&&", "||",
+            "%", "**", "<=", ">=", "<<", ">>", "&", "|", "^", "~", "+=", "-=", "*=", "/=",
+        ];
+        // xorshift, so every run checks the same inputs
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..5000 {
+            let len = next() % 40;
+            let input = (0..len)
+                .map(|_| fragments[(next() % fragments.len() as u64) as usize])
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut p = Parser::new(lexer::Lexer::new(&input));
+            let program = p.parse_program();
+            // The concrete syntax tree is lossless whatever the errors
+            let (tree, cst_program) = crate::cst::parse(&input);
+            assert_eq!(tree.to_string(), input);
+            assert_eq!(cst_program, program);
+            // Formatting keeps the meaning of whatever parses
+            if let Ok(program) = program {
+                let formatted = crate::formatter::format(&input).unwrap();
+                let reparsed = Parser::new(lexer::Lexer::new(&formatted)).parse_program();
+                assert_eq!(reparsed, Ok(program), "{:?} formatted as {:?}", input, formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_expr() {
+        let input = r#""hello world";"#;
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let program = p.parse_program().unwrap();
+        assert_eq!(
+            program.statements[0],
+            Statement::ExpressionStatement(Expression::Str("hello world".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        let input = "let b = @;\nlet a = \"abc;";
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().err().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "unexpected character '@'");
+        assert_eq!(errors[1].to_string(), "unterminated string literal");
+        assert_eq!((errors[1].span().line, errors[1].span().column), (2, 9));
+    }
+
+    #[test]
+    fn test_array_and_index_expr() {
+        let input = "[1, 2 * 2, 3 + 3]; myArray[1 + 1]; [];";
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let program = p.parse_program().unwrap();
+        let int = |x| Box::new(Expression::Integer(x));
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::ExpressionStatement(Expression::Array(vec![
+                    Expression::Integer(1),
+                    Expression::Infix(Token::Asterisk, int(2), int(2)),
+                    Expression::Infix(Token::Plus, int(3), int(3)),
+                ])),
+                Statement::ExpressionStatement(Expression::Index(
+                    Box::new(Expression::Ident("myArray".to_string())),
+                    Box::new(Expression::Infix(Token::Plus, int(1), int(1))),
+                )),
+                Statement::ExpressionStatement(Expression::Array(vec![])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_literal() {
+        let input = r#"{"one": 1, "two": 1 + 1, 3: true}; {}; {"a": 1,}"#;
+        let mut p = Parser::new(lexer::Lexer::new(input));
+        let program = p.parse_program().unwrap();
+        let str = |x: &str| Expression::Str(x.to_string());
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::ExpressionStatement(Expression::Hash(vec![
+                    (str("one"), Expression::Integer(1)),
+                    (str("two"), Expression::Infix(Token::Plus, Box::new(Expression::Integer(1)), Box::new(Expression::Integer(1)))),
+                    (Expression::Integer(3), Expression::Boolean(true)),
+                ])),
+                Statement::ExpressionStatement(Expression::Hash(vec![])),
+                Statement::ExpressionStatement(Expression::Hash(vec![(str("a"), Expression::Integer(1))])),
+            ]
+        );
+
+        let mut p = Parser::new(lexer::Lexer::new(r#"{"a" 1}"#));
+        let errors = p.parse_program().err().unwrap();
+        assert_eq!(errors[0].to_string(), "expected `:`, found `1`");
+    }
 }
@@ -1,29 +1,55 @@
-#[derive(Debug, PartialEq, Eq, Clone)]
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token{
     Illegal,
     EOF,
     // Identifiers + Literals
     Ident(String),
-    Int(i32),
+    Int(i64),
+    Float(f64),
+    Str(String),
 
     // Operators
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     Plus,
     Minus,
     Bang,
     Asterisk,
     Slash,
+    Percent,
+    Power,
     Lt,
     Gt,
+    LtEq,
+    GtEq,
     Eq,
     NotEq,
+    And,
+    Or,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Shl,
+    Shr,
+    DotDot,
+    DotDotEq,
     // Delimiters
     Comma,
     Semicolon,
+    Colon,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
 
     // Keywords
     Function,
@@ -32,14 +58,127 @@ pub enum Token{
     False,
     If,
     Else,
-    Return
+    Return,
+    While,
+    Break,
+    Continue,
+    For,
+    In,
+
+    // Trivia, only produced by `Lexer::with_trivia`
+    Whitespace(String),
+    Comment(String),
+}
+
+impl Token {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Token::Whitespace(_) | Token::Comment(_))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Illegal => write!(f, "ILLEGAL"),
+            Token::EOF => write!(f, "EOF"),
+            Token::Ident(x) => write!(f, "{}", x),
+            Token::Int(x) => write!(f, "{}", x),
+            // Debug keeps the `.0` on whole numbers, so it reads back as a float
+            Token::Float(x) => write!(f, "{:?}", x),
+            Token::Str(x) => write_str_literal(f, x),
+            Token::Assign => write!(f, "="),
+            Token::PlusAssign => write!(f, "+="),
+            Token::MinusAssign => write!(f, "-="),
+            Token::AsteriskAssign => write!(f, "*="),
+            Token::SlashAssign => write!(f, "/="),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Bang => write!(f, "!"),
+            Token::Asterisk => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
+            Token::Power => write!(f, "**"),
+            Token::Lt => write!(f, "<"),
+            Token::Gt => write!(f, ">"),
+            Token::LtEq => write!(f, "<="),
+            Token::GtEq => write!(f, ">="),
+            Token::Eq => write!(f, "=="),
+            Token::NotEq => write!(f, "!="),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Ampersand => write!(f, "&"),
+            Token::Pipe => write!(f, "|"),
+            Token::Caret => write!(f, "^"),
+            Token::Tilde => write!(f, "~"),
+            Token::Shl => write!(f, "<<"),
+            Token::Shr => write!(f, ">>"),
+            Token::DotDot => write!(f, ".."),
+            Token::DotDotEq => write!(f, "..="),
+            Token::Comma => write!(f, ","),
+            Token::Semicolon => write!(f, ";"),
+            Token::Colon => write!(f, ":"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::LBrace => write!(f, "{{"),
+            Token::RBrace => write!(f, "}}"),
+            Token::LBracket => write!(f, "["),
+            Token::RBracket => write!(f, "]"),
+            Token::Function => write!(f, "fn"),
+            Token::Let => write!(f, "let"),
+            Token::True => write!(f, "true"),
+            Token::False => write!(f, "false"),
+            Token::If => write!(f, "if"),
+            Token::Else => write!(f, "else"),
+            Token::Return => write!(f, "return"),
+            Token::While => write!(f, "while"),
+            Token::Break => write!(f, "break"),
+            Token::Continue => write!(f, "continue"),
+            Token::For => write!(f, "for"),
+            Token::In => write!(f, "in"),
+            Token::Whitespace(x) | Token::Comment(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// Writes `s` as a string literal that lexes back to `s`.
+pub(crate) fn write_str_literal(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// A region of the source text. `start` and `end` are byte offsets (`end` is
+/// exclusive), `line` and `column` are 1-based and point at `start`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::lexer::Lexer;
-    use crate::token::Token;
+    use crate::lexer::{tokenize, Lexer};
+    use crate::token::{Span, Token};
     #[test]
     fn next_token() {
         let input = "let five = 5;
@@ -111,4 +250,307 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn next_spanned_token() {
+        let input = "let x = 5;\n  x + 10";
+        let tests = [
+            (Token::Let, Span { start: 0, end: 3, line: 1, column: 1 }),
+            (Token::Ident("x".to_string()), Span { start: 4, end: 5, line: 1, column: 5 }),
+            (Token::Assign, Span { start: 6, end: 7, line: 1, column: 7 }),
+            (Token::Int(5), Span { start: 8, end: 9, line: 1, column: 9 }),
+            (Token::Semicolon, Span { start: 9, end: 10, line: 1, column: 10 }),
+            (Token::Ident("x".to_string()), Span { start: 13, end: 14, line: 2, column: 3 }),
+            (Token::Plus, Span { start: 15, end: 16, line: 2, column: 5 }),
+            (Token::Int(10), Span { start: 17, end: 19, line: 2, column: 7 }),
+            (Token::EOF, Span { start: 19, end: 19, line: 2, column: 9 }),
+        ];
+        let mut lexer = Lexer::new(input);
+        for (token, span) in tests {
+            let spanned = lexer.next_spanned_token();
+            assert_eq!(spanned.token, token);
+            assert_eq!(spanned.span, span, "Wrong span for {:?}", token);
+        }
+    }
+
+    #[test]
+    fn string_literals() {
+        let input = r#""foobar" "foo bar" "a\n\tb" "say \"hi\" \\o/" "\u{1F600}\u{e9}" "héllo""#;
+        let tests = [
+            Token::Str("foobar".to_string()),
+            Token::Str("foo bar".to_string()),
+            Token::Str("a\n\tb".to_string()),
+            Token::Str("say \"hi\" \\o/".to_string()),
+            Token::Str("\u{1F600}\u{e9}".to_string()),
+            Token::Str("héllo".to_string()),
+            Token::EOF,
+        ];
+        let mut lexer = Lexer::new(input);
+        for token in tests {
+            assert_eq!(lexer.next_token(), token);
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn string_literal_errors() {
+        let mut lexer = Lexer::new(r#"let s = "a\qb\u{110000}"; "open"#);
+        let tokens: Vec<Token> = (0..7).map(|_| lexer.next_token()).collect();
+        assert_eq!(tokens[3], Token::Str("a\\qb\\u{110000}".to_string()));
+        assert_eq!(tokens[5], Token::Illegal);
+        assert_eq!(tokens[6], Token::EOF);
+        let errors: Vec<String> = lexer.take_errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "invalid escape sequence `\\q`",
+                "invalid escape sequence `\\u{110000}`",
+                "unterminated string literal",
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_literal_overflow() {
+        let mut lexer = Lexer::new("9223372036854775807 9223372036854775808");
+        assert_eq!(lexer.next_token(), Token::Int(i64::MAX));
+        assert_eq!(lexer.next_token(), Token::Illegal);
+        assert_eq!(lexer.next_token(), Token::EOF);
+        let errors = lexer.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "integer literal 9223372036854775808 is too large");
+        assert_eq!(errors[0].span(), Span { start: 20, end: 39, line: 1, column: 21 });
+    }
+
+    #[test]
+    fn float_literals() {
+        let input = "3.25 0.5 1e-9 2.5E+3 7e2 10 1.x 1e";
+        let tests = [
+            Token::Float(3.25),
+            Token::Float(0.5),
+            Token::Float(1e-9),
+            Token::Float(2500.0),
+            Token::Float(700.0),
+            Token::Int(10),
+            Token::Int(1),
+            Token::Illegal,
+            Token::Ident("x".to_string()),
+            Token::Int(1),
+            Token::Ident("e".to_string()),
+            Token::EOF,
+        ];
+        let mut lexer = Lexer::new(input);
+        for token in tests {
+            assert_eq!(lexer.next_token(), token);
+        }
+        let mut lexer = Lexer::new("1e999");
+        assert_eq!(lexer.next_token(), Token::Illegal);
+        assert_eq!(lexer.take_errors()[0].to_string(), "float literal 1e999 is too large");
+    }
+
+    #[test]
+    fn comments() {
+        let input = "// leading comment
+let x = 10 / 2; // trailing comment
+/* block
+   comment */ x /* inline */ + /* nested /* inner */ still comment */ 1
+//";
+        let tests = [
+            Token::Let,
+            Token::Ident("x".to_string()),
+            Token::Assign,
+            Token::Int(10),
+            Token::Slash,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Ident("x".to_string()),
+            Token::Plus,
+            Token::Int(1),
+            Token::EOF,
+        ];
+        let mut lexer = Lexer::new(input);
+        for token in tests {
+            assert_eq!(lexer.next_token(), token);
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let mut lexer = Lexer::new("let x = 1;\n  /* outer /* inner */ never closed");
+        let tokens: Vec<Token> = (0..6).map(|_| lexer.next_token()).collect();
+        assert_eq!(tokens[5], Token::EOF);
+        let errors = lexer.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "unterminated block comment");
+        assert_eq!(errors[0].span(), Span { start: 13, end: 15, line: 2, column: 3 });
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let mut lexer = Lexer::new("let café = \"héllo\";\nlet 名前 = café_2;");
+        let expected = [
+            (Token::Let, Span { start: 0, end: 3, line: 1, column: 1 }),
+            (Token::Ident("café".to_string()), Span { start: 4, end: 9, line: 1, column: 5 }),
+            (Token::Assign, Span { start: 10, end: 11, line: 1, column: 10 }),
+            (Token::Str("héllo".to_string()), Span { start: 12, end: 20, line: 1, column: 12 }),
+            (Token::Semicolon, Span { start: 20, end: 21, line: 1, column: 19 }),
+            (Token::Let, Span { start: 22, end: 25, line: 2, column: 1 }),
+            (Token::Ident("名前".to_string()), Span { start: 26, end: 32, line: 2, column: 5 }),
+            (Token::Assign, Span { start: 33, end: 34, line: 2, column: 8 }),
+            (Token::Ident("café_2".to_string()), Span { start: 35, end: 42, line: 2, column: 10 }),
+            (Token::Semicolon, Span { start: 42, end: 43, line: 2, column: 16 }),
+            (Token::EOF, Span { start: 43, end: 43, line: 2, column: 17 }),
+        ];
+        for (token, span) in expected {
+            let spanned = lexer.next_spanned_token();
+            assert_eq!(spanned.token, token);
+            assert_eq!(spanned.span, span, "span of {:?}", token);
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn non_identifier_chars() {
+        let mut lexer = Lexer::new("ä 🦀 ¬ \0");
+        assert_eq!(lexer.next_token(), Token::Ident("ä".to_string()));
+        let tokens: Vec<Token> = (0..4).map(|_| lexer.next_token()).collect();
+        assert_eq!(tokens, [Token::Illegal, Token::Illegal, Token::Illegal, Token::EOF]);
+        let errors = lexer.take_errors();
+        assert_eq!(errors[0].to_string(), "unexpected character '🦀'");
+        assert_eq!(errors[0].span(), Span { start: 3, end: 7, line: 1, column: 3 });
+        assert_eq!(errors[1].span(), Span { start: 8, end: 10, line: 1, column: 5 });
+        assert_eq!(errors[2].to_string(), "unexpected character '\\0'");
+    }
+
+    #[test]
+    fn loop_keywords() {
+        let tokens: Vec<Token> = Lexer::new("while break continue whiles for in").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::While,
+                Token::Break,
+                Token::Continue,
+                Token::Ident("whiles".to_string()),
+                Token::For,
+                Token::In,
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let tokens: Vec<Token> = Lexer::new("0..10 1..=n 1.5..2 x . y").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Int(0),
+                Token::DotDot,
+                Token::Int(10),
+                Token::Int(1),
+                Token::DotDotEq,
+                Token::Ident("n".to_string()),
+                Token::Float(1.5),
+                Token::DotDot,
+                Token::Int(2),
+                Token::Ident("x".to_string()),
+                Token::Illegal,
+                Token::Ident("y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        let tokens: Vec<Token> = Lexer::new("a&&b || c @ d").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Ident("a".to_string()),
+                Token::And,
+                Token::Ident("b".to_string()),
+                Token::Or,
+                Token::Ident("c".to_string()),
+                Token::Illegal,
+                Token::Ident("d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let tokens: Vec<Token> = Lexer::new("% ** * <= < << >= > >> & | ^ ~ +").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Percent,
+                Token::Power,
+                Token::Asterisk,
+                Token::LtEq,
+                Token::Lt,
+                Token::Shl,
+                Token::GtEq,
+                Token::Gt,
+                Token::Shr,
+                Token::Ampersand,
+                Token::Pipe,
+                Token::Caret,
+                Token::Tilde,
+                Token::Plus,
+            ]
+        );
+        // Longest match
+        let tokens: Vec<Token> = Lexer::new("***<<=").collect();
+        assert_eq!(tokens, [Token::Power, Token::Asterisk, Token::Shl, Token::Assign]);
+    }
+
+    #[test]
+    fn assignment_operators() {
+        let tokens: Vec<Token> = Lexer::new("= += -= *= /= **= == - =").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Assign,
+                Token::PlusAssign,
+                Token::MinusAssign,
+                Token::AsteriskAssign,
+                Token::SlashAssign,
+                Token::Power,
+                Token::Assign,
+                Token::Eq,
+                Token::Minus,
+                Token::Assign,
+            ]
+        );
+    }
+
+    #[test]
+    fn token_stream() {
+        let tokens: Vec<Token> = Lexer::new("let x = 5;").collect();
+        assert_eq!(
+            tokens,
+            [Token::Let, Token::Ident("x".to_string()), Token::Assign, Token::Int(5), Token::Semicolon]
+        );
+        assert_eq!(Lexer::new("  // nothing here").next(), None);
+
+        let tokens = tokenize("a + 1");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[2].token, Token::Int(1));
+        assert_eq!(tokens[2].span, Span { start: 4, end: 5, line: 1, column: 5 });
+    }
+
+    #[test]
+    fn peek_n() {
+        let mut lexer = Lexer::new("a(b) @");
+        assert_eq!(lexer.peek_n(2).token, Token::Ident("b".to_string()));
+        assert_eq!(lexer.peek_n(0).token, Token::Ident("a".to_string()));
+        assert_eq!(lexer.peek_n(6).token, Token::EOF);
+        // Peeking reports errors ahead of the token
+        assert_eq!(lexer.take_errors().len(), 1);
+        assert_eq!(lexer.next_token(), Token::Ident("a".to_string()));
+        assert_eq!(lexer.next_spanned_token().span, Span { start: 1, end: 2, line: 1, column: 2 });
+        let rest: Vec<Token> = lexer.collect();
+        assert_eq!(rest, [Token::Ident("b".to_string()), Token::RParen, Token::Illegal]);
+    }
 }